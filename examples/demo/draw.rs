@@ -5,17 +5,21 @@ use forma::{Order, PathBuilder};
 use parley::FontContext;
 
 use crate::RunContext;
+use tted::cache::BitmapCache;
 use tted::helpers::AffineHelpers;
+use tted::layers::LayerAllocator;
 use tted::rich_text::{RichText, StyleProperty};
 use tted::{
     layout_types::{Widget, WidgetContext},
     text::Text,
-    types::Size,
+    types::{Rect, Size},
 };
 
 pub struct Drawer {
     widget: Text,
     font_context: FontContext,
+    bitmaps: BitmapCache,
+    layers: LayerAllocator,
     transform: AffineTransform,
     needs_composition: bool,
     size: Size,
@@ -66,6 +70,9 @@ impl Drawer {
             widget: text,
             transform,
             font_context: context,
+            bitmaps: BitmapCache::new(),
+            // 0 is the debug layer
+            layers: LayerAllocator::with_range(1..u32::MAX),
             needs_composition: true,
             size: Size { w: 1000., h: 1000. },
             debug_rect: false,
@@ -85,7 +92,7 @@ impl crate::App for Drawer {
 
     fn update(&mut self, context: &RunContext<'_>) {
         if let Some(delta) = context.mouse.wheel {
-            self.transform = self.transform.scaled((delta.y / 1000.) as f32);
+            self.transform = self.transform.scaled(1. + (delta.y / 1000.) as f32);
             self.needs_composition = true;
         }
 
@@ -106,52 +113,36 @@ impl crate::App for Drawer {
 
         let (w, h) = (self.size.w, self.size.h);
 
-        let mut index = 2;
-
         let mut layout_context = WidgetContext {
             font_context: &mut self.font_context,
             transform: &self.transform,
-            index: &mut index,
-            clip: self.clip,
+            layers: &mut self.layers,
+            bitmaps: &self.bitmaps,
+            clip: self
+                .clip
+                .then(|| Rect::new(Point::new(0., 0.), Size::new(w, h))),
+            viewport: Some(Rect::new(Point::new(0., 0.), Size::new(w, h))),
         };
 
         let size = Size::new(500., 5300.);
 
         // FIXME: We get size back here, do something with it?
-        self.widget.layout(&mut layout_context, size);
-
-        // Now we know how many layers to clip
-        if self.clip {
-            clip_rect(composition, 0, (*layout_context.index) as usize + 1, w, h);
+        if let Err(error) = self.widget.layout(&mut layout_context, size) {
+            eprintln!("Could not layout text: {error}");
+            return;
         }
 
         self.widget
             .compose(&layout_context, composition, context.elapsed);
 
         if self.debug_rect {
-            debug_rect(composition, 1, w, h);
+            debug_rect(composition, 0, w, h);
         }
 
         self.needs_composition = false;
     }
 }
 
-fn clip_rect(composition: &mut Composition, index: u32, count: usize, w: f32, h: f32) {
-    let mut builder = PathBuilder::new();
-    builder.move_to(Point::new(0., 0.));
-    builder.line_to(Point::new(w, 0.0));
-    builder.line_to(Point::new(w, h));
-    builder.line_to(Point::new(0., h));
-    builder.line_to(Point::new(0., 0.));
-    let path = builder.build();
-
-    let layer = composition.get_mut_or_insert_default(Order::new(index).unwrap());
-    layer.insert(&path).set_props(Props {
-        fill_rule: FillRule::NonZero,
-        func: forma::styling::Func::Clip(count),
-    });
-}
-
 fn debug_rect(composition: &mut Composition, index: u32, w: f32, h: f32) {
     let mut builder = PathBuilder::new();
     builder.move_to(Point::new(0., 0.));
@@ -0,0 +1,104 @@
+//! Measures the demo's text on the CPU renderer: how many forma layers it
+//! takes, how long layout and a frame take and how much memory the process
+//! peaks at. Run with `cargo run --release --example layers`.
+
+use std::time::{Duration, Instant};
+
+use forma::cpu::buffer::layout::LinearLayout;
+use forma::cpu::buffer::BufferBuilder;
+use forma::cpu::{Renderer, RGBA};
+use forma::prelude::*;
+use parley::layout::Alignment;
+use parley::{FontContext, LayoutContext};
+
+use tted::cache::BitmapCache;
+use tted::layers::LayerAllocator;
+use tted::layout_types::{Widget, WidgetContext};
+use tted::rich_text::{RichText, StyleProperty};
+use tted::text::Text;
+use tted::types::Size;
+
+const WIDTH: usize = 1000;
+const HEIGHT: usize = 1000;
+
+fn main() {
+    let mut rich_text = RichText::new([
+        StyleProperty::Font("Archivo Black"),
+        StyleProperty::FontSize(30.),
+    ]);
+    rich_text.add_str("Forma tted");
+    for _ in 0..5 {
+        rich_text.add_newline();
+        rich_text.add_single(
+            include_str!("../LICENSE"),
+            StyleProperty::Font("Roboto Regular"),
+        );
+    }
+
+    let mut font_context = FontContext::new();
+    font_context.register_fonts(include_bytes!("../assets/ArchivoBlack-Regular.ttf").to_vec());
+    font_context.register_fonts(include_bytes!("../assets/Roboto-Regular.ttf").to_vec());
+
+    // the lines and glyph runs the text is shaped into, each glyph run took a
+    // layer of its own before outlines were batched
+    let mut layout = rich_text.build(&mut LayoutContext::new(), &mut font_context);
+    layout.break_all_lines(Some(WIDTH as f32), Alignment::Start);
+    let line_count = layout.lines().count();
+    let run_count: usize = layout.lines().map(|line| line.glyph_runs().count()).sum();
+
+    let mut text = Text::new(rich_text);
+    let mut layers = LayerAllocator::new();
+    let mut composition = Composition::new();
+    let transform = AffineTransform::default();
+    let mut ctx = WidgetContext {
+        font_context: &mut font_context,
+        transform: &transform,
+        layers: &mut layers,
+        bitmaps: &BitmapCache::new(),
+        clip: None,
+        viewport: None,
+    };
+
+    let start = Instant::now();
+    text.layout(&mut ctx, Size::new(WIDTH as f32, f32::INFINITY))
+        .expect("the text fits into forma's layers");
+    let layout_time = start.elapsed();
+
+    let start = Instant::now();
+    text.compose(&ctx, &mut composition, Duration::ZERO);
+    let mut buffer = vec![0u8; WIDTH * HEIGHT * 4];
+    let mut buffer_layout = LinearLayout::new(WIDTH, WIDTH * 4, HEIGHT);
+    Renderer::new().render(
+        &mut composition,
+        &mut BufferBuilder::new(&mut buffer, &mut buffer_layout).build(),
+        RGBA,
+        Color {
+            r: 1.,
+            g: 1.,
+            b: 1.,
+            a: 1.,
+        },
+        None,
+    );
+    let frame_time = start.elapsed();
+
+    let layer_count = text.layers().map_or(0, |range| range.len());
+    println!("lines:      {line_count}");
+    println!("glyph runs: {run_count}");
+    println!("layers:     {layer_count}");
+    println!("layout:     {layout_time:?}");
+    println!("first frame: {frame_time:?}");
+    match peak_memory() {
+        Some(peak) => println!("peak memory: {peak}"),
+        None => println!("peak memory: only measured on Linux"),
+    }
+}
+
+/// The process's peak resident set size as reported by the kernel
+fn peak_memory() -> Option<String> {
+    let status = std::fs::read_to_string("/proc/self/status").ok()?;
+    status
+        .lines()
+        .find_map(|line| line.strip_prefix("VmHWM:"))
+        .map(|value| value.trim().to_owned())
+}
@@ -0,0 +1,106 @@
+use std::cell::RefCell;
+use std::collections::HashMap;
+use std::hash::Hash;
+
+use forma::styling::Image;
+use parley::swash::zeno::Placement;
+
+use crate::layout_types::CacheKey;
+
+/// A color bitmap and where swash places it relative to the glyph origin
+#[derive(Clone)]
+pub struct Bitmap {
+    pub placement: Placement,
+    pub image: Image,
+}
+
+/// The glyph of an emoji silhouette, with its blur radius in pixels and the
+/// bits of its color's channels
+pub type SilhouetteKey = (CacheKey, u32, [u32; 4]);
+
+/// Color bitmaps of emoji glyphs, keyed by font, glyph and strike size, or
+/// their shadow silhouettes keyed by `SilhouetteKey`. Every occurrence of the
+/// same glyph shares one `Image`, so the renderers only have to upload it once.
+/// Widgets share the cache through `WidgetContext::bitmaps`, which they also
+/// fill while composing, so it is filled through a shared reference.
+pub struct BitmapCache<K = CacheKey> {
+    images: RefCell<HashMap<K, Option<Bitmap>>>,
+}
+
+impl<K> Default for BitmapCache<K> {
+    fn default() -> Self {
+        Self {
+            images: RefCell::new(HashMap::new()),
+        }
+    }
+}
+
+impl<K: Eq + Hash> BitmapCache<K> {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Returns the cached bitmap for `key`, calling `rasterize` only the first
+    /// time the key is seen. Glyphs without a color bitmap are cached as `None`
+    /// so they are not rasterized again either.
+    pub fn get_or_insert_with(
+        &self,
+        key: K,
+        rasterize: impl FnOnce() -> Option<Bitmap>,
+    ) -> Option<Bitmap> {
+        self.images
+            .borrow_mut()
+            .entry(key)
+            .or_insert_with(rasterize)
+            .clone()
+    }
+
+    /// Number of distinct glyph bitmaps held by the cache
+    pub fn len(&self) -> usize {
+        self.images
+            .borrow()
+            .values()
+            .filter(|bitmap| bitmap.is_some())
+            .count()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    pub fn clear(&self) {
+        self.images.borrow_mut().clear();
+    }
+}
+
+/// Alpha coverage of a glyph rasterized with swash's mask renderer
+pub struct Mask {
+    pub placement: Placement,
+    pub coverage: Vec<u8>,
+}
+
+/// Rasterized glyph masks, keyed by font, glyph and pixel size. Used by
+/// `RasterMode::Hybrid` for small text.
+#[derive(Default)]
+pub struct MaskCache {
+    masks: HashMap<CacheKey, Option<Mask>>,
+}
+
+impl MaskCache {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Rasterizes the mask for `key` unless it is already cached
+    pub fn insert_with(&mut self, key: CacheKey, rasterize: impl FnOnce() -> Option<Mask>) {
+        self.masks.entry(key).or_insert_with(rasterize);
+    }
+
+    pub fn get(&self, key: &CacheKey) -> Option<&Mask> {
+        self.masks.get(key).and_then(|mask| mask.as_ref())
+    }
+
+    pub fn clear(&mut self) {
+        self.masks.clear();
+    }
+}
@@ -1,11 +1,13 @@
 use forma::math::{AffineTransform, Point};
-use forma::styling::Image;
+use forma::styling::{Color, Image};
 use forma::Path;
 use parley::swash::scale::image::{Content, Image as SwashImage};
 use parley::swash::zeno::Vector;
-use parley::swash::zeno::{Bounds, Command};
+use parley::swash::zeno::{Bounds, Command, Join, PathData, Placement, Stroke};
 
+use crate::cache::{Bitmap, Mask};
 use crate::helpers::AffineHelpers;
+use crate::layout_types::StrokeJoin;
 
 pub trait Convert {
     type Output;
@@ -34,62 +36,236 @@ impl Convert for (f32, f32) {
     }
 }
 
-impl Convert for SwashImage {
-    type Output = Option<Image>;
+/// CPAL palette entries are sRGB, forma colors are linear
+impl Convert for [u8; 4] {
+    type Output = Color;
+
     fn convert(self) -> Self::Output {
-        use image::{DynamicImage, RgbaImage};
+        fn linear(value: u8) -> f32 {
+            let value = value as f32 / 255.;
+            if value <= 0.04045 {
+                value / 12.92
+            } else {
+                ((value + 0.055) / 1.055).powf(2.4)
+            }
+        }
+        Color {
+            r: linear(self[0]),
+            g: linear(self[1]),
+            b: linear(self[2]),
+            a: self[3] as f32 / 255.,
+        }
+    }
+}
+
+/// The reverse, for images and SVG, which are sRGB. Alpha is left to the caller.
+impl Convert for Color {
+    type Output = [u8; 3];
+
+    fn convert(self) -> Self::Output {
+        fn srgb(value: f32) -> u8 {
+            let value = value.clamp(0., 1.);
+            let value = if value <= 0.0031308 {
+                value * 12.92
+            } else {
+                1.055 * value.powf(1. / 2.4) - 0.055
+            };
+            (value * 255.).round() as u8
+        }
+        [srgb(self.r), srgb(self.g), srgb(self.b)]
+    }
+}
+
+impl Convert for StrokeJoin {
+    type Output = Join;
+
+    fn convert(self) -> Self::Output {
+        match self {
+            StrokeJoin::Miter => Join::Miter,
+            StrokeJoin::Round => Join::Round,
+            StrokeJoin::Bevel => Join::Bevel,
+        }
+    }
+}
 
+impl Convert for SwashImage {
+    type Output = Option<Bitmap>;
+    fn convert(self) -> Self::Output {
         // we only support color bitmaps here
         if self.content != Content::Color {
             return None;
         }
 
-        let rgba_image =
-            RgbaImage::from_vec(self.placement.width, self.placement.height, self.data)?;
-        let w = rgba_image.width();
-        let h = rgba_image.height();
-
-        let dynamic_image = DynamicImage::ImageRgba8(rgba_image);
+        let (w, h) = (
+            self.placement.width as usize,
+            self.placement.height as usize,
+        );
+        if self.data.len() != w * h * 4 {
+            return None;
+        }
 
-        let data: Vec<_> = dynamic_image
-            .to_rgba8()
-            .pixels()
-            .map(|p| [p.0[0], p.0[1], p.0[2], p.0[3]])
+        let data: Vec<_> = self
+            .data
+            .chunks_exact(4)
+            .map(|p| [p[0], p[1], p[2], p[3]])
             .collect();
-        Image::from_srgba(&data[..], w as usize, h as usize).ok()
+        let image = Image::from_srgba(&data[..], w, h).ok()?;
+        Some(Bitmap {
+            placement: self.placement,
+            image,
+        })
+    }
+}
+
+/// The silhouette of a color bitmap in a single `color`, with its edge blurred
+/// over `radius` pixels. The image grows by `radius` on every side to make
+/// room for the blur, and the placement moves with it.
+pub fn convert_silhouette(image: SwashImage, color: Color, radius: u32) -> Option<Bitmap> {
+    let mut placement = image.placement;
+    let (w, h) = (placement.width as usize, placement.height as usize);
+    if image.content != Content::Color || image.data.len() != w * h * 4 {
+        return None;
+    }
+    let r = radius as usize;
+    let (width, height) = (w + 2 * r, h + 2 * r);
+    let mut alpha = vec![0f32; width * height];
+    for (index, pixel) in image.data.chunks_exact(4).enumerate() {
+        alpha[(index / w + r) * width + index % w + r] = pixel[3] as f32 / 255.;
+    }
+    if r > 0 {
+        box_blur(&mut alpha, width, height, r);
+    }
+
+    let [red, green, blue] = color.convert();
+    let data: Vec<_> = alpha
+        .iter()
+        .map(|alpha| {
+            let alpha = (alpha * color.a.clamp(0., 1.) * 255.).round() as u8;
+            [red, green, blue, alpha]
+        })
+        .collect();
+    let image = Image::from_srgba(&data[..], width, height).ok()?;
+    placement.left -= radius as i32;
+    placement.top += radius as i32;
+    placement.width = width as u32;
+    placement.height = height as u32;
+    Some(Bitmap { placement, image })
+}
+
+/// Blurs `values`, rows of `width`, with a box of `2 * radius + 1` pixels
+fn box_blur(values: &mut [f32], width: usize, height: usize, radius: usize) {
+    let mut line = Vec::with_capacity(width.max(height));
+    for y in 0..height {
+        line.clear();
+        line.extend_from_slice(&values[y * width..(y + 1) * width]);
+        blur_line(&line, radius, |x, value| values[y * width + x] = value);
+    }
+    for x in 0..width {
+        line.clear();
+        line.extend((0..height).map(|y| values[y * width + x]));
+        blur_line(&line, radius, |y, value| values[y * width + x] = value);
     }
 }
 
+/// Averages every value of `line` with `radius` neighbors on each side, where
+/// values beyond the ends count as zero
+fn blur_line(line: &[f32], radius: usize, mut write: impl FnMut(usize, f32)) {
+    let window = (2 * radius + 1) as f32;
+    let mut sum: f32 = line.iter().take(radius).sum();
+    for index in 0..line.len() {
+        if let Some(value) = line.get(index + radius) {
+            sum += value;
+        }
+        if index > radius {
+            sum -= line[index - radius - 1];
+        }
+        write(index, sum.max(0.) / window);
+    }
+}
+
+/// Turns a swash alpha mask into a single channel of coverage
+pub fn convert_mask(image: SwashImage) -> Option<Mask> {
+    let pixels = image.placement.width as usize * image.placement.height as usize;
+    if image.content != Content::Mask || image.data.len() != pixels {
+        return None;
+    }
+    Some(Mask {
+        placement: image.placement,
+        coverage: image.data,
+    })
+}
+
 pub fn convert_path(
     value: impl Iterator<Item = Command>,
     transform: &AffineTransform,
 ) -> forma::Path {
     let mut builder = forma::PathBuilder::default();
-    for entry in value {
-        match entry {
-            Command::MoveTo(p) => {
-                builder.move_to(transform.transform_point(p.convert()));
-            }
-            Command::LineTo(p) => {
-                builder.line_to(transform.transform_point(p.convert()));
-            }
-            Command::QuadTo(p1, p2) => {
-                builder.quad_to(
-                    transform.transform_point(p1.convert()),
-                    transform.transform_point(p2.convert()),
-                );
-            }
-            Command::CurveTo(p1, p2, p3) => {
-                builder.cubic_to(
-                    transform.transform_point(p1.convert()),
-                    transform.transform_point(p2.convert()),
-                    transform.transform_point(p3.convert()),
-                );
-            }
-            Command::Close => {}
+    place_commands(value, transform, &mut builder);
+    builder.build()
+}
+
+/// Receives the segments of an outline once `place_commands` has placed them
+pub(crate) trait PathSink {
+    fn move_to(&mut self, p: Point);
+    fn line_to(&mut self, p: Point);
+    fn quad_to(&mut self, p1: Point, p2: Point);
+    fn cubic_to(&mut self, p1: Point, p2: Point, p3: Point);
+    fn close(&mut self);
+}
+
+/// forma closes every contour on its own
+impl PathSink for forma::PathBuilder {
+    fn move_to(&mut self, p: Point) {
+        forma::PathBuilder::move_to(self, p);
+    }
+
+    fn line_to(&mut self, p: Point) {
+        forma::PathBuilder::line_to(self, p);
+    }
+
+    fn quad_to(&mut self, p1: Point, p2: Point) {
+        forma::PathBuilder::quad_to(self, p1, p2);
+    }
+
+    fn cubic_to(&mut self, p1: Point, p2: Point, p3: Point) {
+        forma::PathBuilder::cubic_to(self, p1, p2, p3);
+    }
+
+    fn close(&mut self) {}
+}
+
+/// Feeds the swash `commands` to `sink` with their points placed by
+/// `transform`. forma paths and SVG path data are both built this way, so
+/// they always agree.
+pub(crate) fn place_commands(
+    commands: impl Iterator<Item = Command>,
+    transform: &AffineTransform,
+    sink: &mut impl PathSink,
+) {
+    let place = |p: Vector| transform.transform_point(p.convert());
+    for command in commands {
+        match command {
+            Command::MoveTo(p) => sink.move_to(place(p)),
+            Command::LineTo(p) => sink.line_to(place(p)),
+            Command::QuadTo(p1, p2) => sink.quad_to(place(p1), place(p2)),
+            Command::CurveTo(p1, p2, p3) => sink.cubic_to(place(p1), place(p2), place(p3)),
+            Command::Close => sink.close(),
         }
     }
-    builder.build()
+}
+
+/// The area a stroke of `width` along `data` covers, as commands to fill.
+/// forma only fills paths, so strokes are drawn as the outline zeno's stroker
+/// puts around them. The result is meant for the non-zero fill rule.
+pub fn stroke_commands(data: impl PathData, width: f32, join: StrokeJoin) -> Vec<Command> {
+    let mut commands = Vec::new();
+    parley::swash::zeno::apply(
+        data,
+        Stroke::new(width).join(join.convert()),
+        None,
+        &mut commands,
+    );
+    commands
 }
 
 pub fn convert_bounds(bounds: &Bounds, transform: &AffineTransform) -> Path {
@@ -98,21 +274,22 @@ pub fn convert_bounds(bounds: &Bounds, transform: &AffineTransform) -> Path {
     }
     let mut builder = forma::PathBuilder::default();
     builder.move_to(convert(bounds.min.x, bounds.min.y, transform));
-    builder.line_to(convert(
-        bounds.min.x + bounds.max.x,
-        bounds.min.y,
-        transform,
-    ));
-    builder.line_to(convert(
-        bounds.min.x + bounds.max.x,
-        bounds.min.y + bounds.max.y,
-        transform,
-    ));
-    builder.line_to(convert(
-        bounds.min.x,
-        bounds.min.y + bounds.max.y,
-        transform,
-    ));
+    builder.line_to(convert(bounds.max.x, bounds.min.y, transform));
+    builder.line_to(convert(bounds.max.x, bounds.max.y, transform));
+    builder.line_to(convert(bounds.min.x, bounds.max.y, transform));
     builder.line_to(convert(bounds.min.x, bounds.min.y, transform));
     builder.build()
 }
+
+/// The quad covered by a bitmap with `placement`. Like outlines, placements
+/// are y-up with `top` measured from the baseline, so `transform` takes the
+/// same mirroring as `convert_path`, plus the scale from bitmap pixels to the
+/// outline's units.
+pub fn convert_placement(placement: &Placement, transform: &AffineTransform) -> Path {
+    let (left, top) = (placement.left as f32, placement.top as f32);
+    let bounds = Bounds {
+        min: Vector::new(left, top - placement.height as f32),
+        max: Vector::new(left + placement.width as f32, top),
+    };
+    convert_bounds(&bounds, transform)
+}
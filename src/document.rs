@@ -0,0 +1,388 @@
+use std::time::Duration;
+
+use forma::prelude::*;
+
+use crate::helpers::AffineHelpers;
+use crate::layers::{LayerAllocator, LayerError, LayerRange};
+use crate::layout_types::{Widget, WidgetContext};
+use crate::raster::RasterMode;
+use crate::rich_text::RichText;
+use crate::text::Text;
+use crate::types::Size;
+
+/// Height of a paragraph before any paragraph has been measured
+const DEFAULT_LINE_HEIGHT: f32 = 20.;
+/// Paragraphs within this many viewport heights around the viewport are shaped
+const SHAPE_AHEAD: f32 = 1.;
+/// Shaped paragraphs further away than this many viewport heights are discarded
+const KEEP_DISTANCE: f32 = 3.;
+/// Number of layers a document reserves for its paragraphs by default
+const DEFAULT_LAYER_BUDGET: u32 = 1 << 14;
+
+struct Paragraph {
+    source: RichText,
+    /// The shaped paragraph, only present near the viewport
+    text: Option<Text>,
+    /// Set when `text` is far away and should be removed on the next compose
+    retire: bool,
+    /// Height from the last layout of `text`, kept after it is discarded
+    measured: Option<f32>,
+    bytes: usize,
+}
+
+/// Running sums over the paragraphs as a Fenwick tree, so offsets and the
+/// paragraph at a height are found in `O(log n)` while measurements come in.
+/// Every paragraph adds its measured height, or one unmeasured paragraph with
+/// its bytes to the sums.
+struct Offsets {
+    /// Node `i` covers the paragraphs `i - lowbit(i)..i`
+    tree: Vec<Sums>,
+}
+
+#[derive(Clone, Copy, Default)]
+struct Sums {
+    measured: f64,
+    unmeasured: f64,
+    bytes: f64,
+}
+
+impl Sums {
+    fn add(self, other: Sums) -> Sums {
+        Sums {
+            measured: self.measured + other.measured,
+            unmeasured: self.unmeasured + other.unmeasured,
+            bytes: self.bytes + other.bytes,
+        }
+    }
+
+    /// The height of the paragraphs, with `line_height` and `per_byte` for
+    /// those that were not measured
+    fn height(&self, line_height: f32, per_byte: f32) -> f32 {
+        (self.measured + self.unmeasured * line_height as f64 + self.bytes * per_byte as f64) as f32
+    }
+}
+
+impl Offsets {
+    fn new(paragraphs: &[Paragraph]) -> Self {
+        let mut tree = vec![Sums::default(); paragraphs.len() + 1];
+        for (index, paragraph) in paragraphs.iter().enumerate() {
+            let node = index + 1;
+            tree[node] = tree[node].add(Self::sums(paragraph));
+            let parent = node + (node & node.wrapping_neg());
+            if parent < tree.len() {
+                tree[parent] = tree[parent].add(tree[node]);
+            }
+        }
+        Self { tree }
+    }
+
+    fn sums(paragraph: &Paragraph) -> Sums {
+        match paragraph.measured {
+            Some(height) => Sums {
+                measured: height as f64,
+                ..Default::default()
+            },
+            None => Sums {
+                unmeasured: 1.,
+                bytes: paragraph.bytes as f64,
+                ..Default::default()
+            },
+        }
+    }
+
+    fn len(&self) -> usize {
+        self.tree.len() - 1
+    }
+
+    /// Replaces the unmeasured paragraph `index` of `bytes` with its `height`
+    fn measure(&mut self, index: usize, bytes: usize, height: f32) {
+        let change = Sums {
+            measured: height as f64,
+            unmeasured: -1.,
+            bytes: -(bytes as f64),
+        };
+        let mut node = index + 1;
+        while node < self.tree.len() {
+            self.tree[node] = self.tree[node].add(change);
+            node += node & node.wrapping_neg();
+        }
+    }
+
+    /// The sums over the first `count` paragraphs
+    fn prefix(&self, count: usize) -> Sums {
+        let mut sums = Sums::default();
+        let mut node = count;
+        while node > 0 {
+            sums = sums.add(self.tree[node]);
+            node &= node - 1;
+        }
+        sums
+    }
+
+    /// The largest `count` whose prefix still `fits`. `fits` has to hold for
+    /// all counts below one it holds for.
+    fn last_fitting(&self, fits: impl Fn(&Sums) -> bool) -> usize {
+        let mut count = 0;
+        let mut sums = Sums::default();
+        let mut step = (self.len() + 1).next_power_of_two() / 2;
+        while step > 0 {
+            if count + step <= self.len() {
+                let next = sums.add(self.tree[count + step]);
+                if fits(&next) {
+                    count += step;
+                    sums = next;
+                }
+            }
+            step /= 2;
+        }
+        count
+    }
+}
+
+/// A widget for very long texts such as logs or books. The content is split into
+/// paragraphs which are only shaped once they come close to
+/// `WidgetContext.viewport`. Paragraphs that were never shaped are placed with
+/// an estimated height, and distant paragraphs drop their glyphs and layers
+/// again. Without a viewport every paragraph is shaped.
+pub struct Document {
+    paragraphs: Vec<Paragraph>,
+    offsets: Offsets,
+    /// Indices of the paragraphs that have a `Text`
+    shaped: Vec<usize>,
+    /// Hands out layers to the paragraphs from the document's own range
+    layers: LayerAllocator,
+    range: Option<LayerRange>,
+    layer_budget: u32,
+    raster_mode: RasterMode,
+    width: Option<f32>,
+    size: Size,
+    measured_count: usize,
+    measured_bytes: usize,
+    measured_height: f32,
+    line_height: Option<f32>,
+}
+
+impl Document {
+    pub fn new(text: RichText) -> Self {
+        let paragraphs: Vec<_> = text
+            .split_paragraphs()
+            .into_iter()
+            .map(|source| Paragraph {
+                bytes: source.len(),
+                source,
+                text: None,
+                retire: false,
+                measured: None,
+            })
+            .collect();
+        Self {
+            offsets: Offsets::new(&paragraphs),
+            paragraphs,
+            shaped: Vec::new(),
+            layers: LayerAllocator::with_range(0..0),
+            range: None,
+            layer_budget: DEFAULT_LAYER_BUDGET,
+            raster_mode: RasterMode::default(),
+            width: None,
+            size: Size::ZERO,
+            measured_count: 0,
+            measured_bytes: 0,
+            measured_height: 0.,
+            line_height: None,
+        }
+    }
+
+    /// Sets how many layers the document reserves for its shaped paragraphs.
+    /// Takes effect before the first layout.
+    pub fn set_layer_budget(&mut self, layers: u32) {
+        self.layer_budget = layers;
+    }
+
+    pub fn set_raster_mode(&mut self, mode: RasterMode) {
+        self.raster_mode = mode;
+        for index in self.shaped.iter() {
+            if let Some(text) = self.paragraphs[*index].text.as_mut() {
+                text.set_raster_mode(mode);
+            }
+        }
+    }
+
+    pub fn paragraph_count(&self) -> usize {
+        self.paragraphs.len()
+    }
+
+    /// Number of paragraphs that are currently shaped
+    pub fn shaped_count(&self) -> usize {
+        self.shaped
+            .iter()
+            .filter(|index| !self.paragraphs[**index].retire)
+            .count()
+    }
+
+    /// The line height and the height per byte beyond it that paragraphs which
+    /// have not been measured yet are estimated with. Both come from the
+    /// paragraphs measured so far.
+    fn estimate(&self) -> (f32, f32) {
+        let line_height = self.line_height.unwrap_or(DEFAULT_LINE_HEIGHT);
+        if self.measured_bytes == 0 {
+            return (line_height, 0.);
+        }
+        let beyond_first_line = self.measured_height - self.measured_count as f32 * line_height;
+        (
+            line_height,
+            (beyond_first_line / self.measured_bytes as f32).max(0.),
+        )
+    }
+
+    /// The distance of paragraph `index` from the top, or the total height for
+    /// the paragraph count
+    fn offset(&self, index: usize) -> f32 {
+        let (line_height, per_byte) = self.estimate();
+        self.offsets.prefix(index).height(line_height, per_byte)
+    }
+
+    fn forget_measurements(&mut self) {
+        for paragraph in self.paragraphs.iter_mut() {
+            paragraph.measured = None;
+        }
+        for index in self.shaped.iter() {
+            let paragraph = &mut self.paragraphs[*index];
+            if let Some(text) = paragraph.text.as_mut() {
+                text.update(paragraph.source.clone());
+            }
+        }
+        self.offsets = Offsets::new(&self.paragraphs);
+        self.measured_count = 0;
+        self.measured_bytes = 0;
+        self.measured_height = 0.;
+        self.line_height = None;
+    }
+
+    /// Index range of the paragraphs overlapping `top..bottom`
+    fn paragraphs_between(&self, top: f32, bottom: f32) -> std::ops::Range<usize> {
+        let (line_height, per_byte) = self.estimate();
+        // paragraphs ending above `top`, and those starting at or above `bottom`
+        let start = self
+            .offsets
+            .last_fitting(|sums| sums.height(line_height, per_byte) < top);
+        let end = if bottom < 0. {
+            0
+        } else {
+            let last = self
+                .offsets
+                .last_fitting(|sums| sums.height(line_height, per_byte) <= bottom);
+            (last + 1).min(self.paragraphs.len())
+        };
+        start..end.max(start)
+    }
+}
+
+impl Widget for Document {
+    fn layout<'a>(
+        &mut self,
+        ctx: &mut WidgetContext<'a>,
+        proposed_size: Size,
+    ) -> Result<Size, LayerError> {
+        if self.range.is_none() {
+            let range = ctx.layers.reserve(self.layer_budget)?;
+            self.layers = LayerAllocator::with_range(range.start()..range.end());
+            self.range = Some(range);
+        }
+        if self.width != Some(proposed_size.w) {
+            self.width = Some(proposed_size.w);
+            self.forget_measurements();
+        }
+
+        let (shape, keep) = match ctx.viewport.zip(ctx.transform.inverse()) {
+            Some((viewport, inverse)) => {
+                let bounds = viewport.transformed(&inverse);
+                let (top, bottom) = (bounds.origin.y, bounds.max_y());
+                let span = bottom - top;
+                (
+                    self.paragraphs_between(top - span * SHAPE_AHEAD, bottom + span * SHAPE_AHEAD),
+                    self.paragraphs_between(
+                        top - span * KEEP_DISTANCE,
+                        bottom + span * KEEP_DISTANCE,
+                    ),
+                )
+            }
+            None => (0..self.paragraphs.len(), 0..self.paragraphs.len()),
+        };
+
+        for index in shape {
+            let paragraph = &mut self.paragraphs[index];
+            paragraph.retire = false;
+            if paragraph.text.is_none() {
+                self.shaped.push(index);
+            }
+            let text = paragraph.text.get_or_insert_with(|| {
+                let mut text = Text::new(paragraph.source.clone());
+                text.set_raster_mode(self.raster_mode);
+                text
+            });
+            let mut paragraph_ctx = WidgetContext {
+                font_context: &mut *ctx.font_context,
+                transform: ctx.transform,
+                layers: &mut self.layers,
+                bitmaps: ctx.bitmaps,
+                clip: ctx.clip,
+                viewport: ctx.viewport,
+            };
+            let size = text.layout(&mut paragraph_ctx, proposed_size)?;
+            if paragraph.measured.is_none() {
+                paragraph.measured = Some(size.h);
+                self.offsets.measure(index, paragraph.bytes, size.h);
+                self.measured_count += 1;
+                self.measured_bytes += paragraph.bytes;
+                self.measured_height += size.h;
+                if size.h > 0. {
+                    let line_height = self.line_height.get_or_insert(size.h);
+                    *line_height = line_height.min(size.h);
+                }
+            }
+        }
+
+        for index in self.shaped.iter() {
+            if !keep.contains(index) {
+                self.paragraphs[*index].retire = true;
+            }
+        }
+
+        // measurements moved the paragraphs below them
+        let height = self.offset(self.paragraphs.len());
+        self.size = Size::new(proposed_size.w, height);
+        Ok(self.size)
+    }
+
+    fn compose<'a>(
+        &mut self,
+        ctx: &WidgetContext<'a>,
+        composition: &mut Composition,
+        _elapsed: Duration,
+    ) {
+        let (line_height, per_byte) = self.estimate();
+        let offsets = &self.offsets;
+        let paragraphs = &mut self.paragraphs;
+        let layers = &mut self.layers;
+        self.shaped.retain(|index| {
+            let paragraph = &mut paragraphs[*index];
+            let Some(text) = paragraph.text.as_mut() else {
+                return false;
+            };
+            if paragraph.retire {
+                text.detach(composition, layers);
+                paragraph.text = None;
+                paragraph.retire = false;
+                return false;
+            }
+            let offset = offsets.prefix(*index).height(line_height, per_byte);
+            let transform = ctx.transform.translated(0., offset);
+            text.compose_with_transform(ctx, &transform, composition);
+            true
+        });
+    }
+
+    fn layers(&self) -> Option<LayerRange> {
+        self.range
+    }
+}
@@ -0,0 +1,105 @@
+use std::fmt;
+use std::path::Path;
+use std::time::Duration;
+
+use forma::cpu::buffer::layout::LinearLayout;
+use forma::cpu::buffer::BufferBuilder;
+use forma::cpu::{Renderer, RGBA};
+use forma::prelude::*;
+use image::{ImageError, RgbaImage};
+use parley::FontContext;
+
+use crate::cache::BitmapCache;
+use crate::helpers::AffineHelpers;
+use crate::layers::{LayerAllocator, LayerError};
+use crate::layout_types::{Widget, WidgetContext};
+use crate::rich_text::RichText;
+use crate::text::Text;
+use crate::types::Size;
+
+#[derive(Debug)]
+pub enum HeadlessError {
+    Layers(LayerError),
+    Image(ImageError),
+}
+
+impl fmt::Display for HeadlessError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            HeadlessError::Layers(error) => write!(f, "could not lay out the text: {error}"),
+            HeadlessError::Image(error) => write!(f, "could not write the image: {error}"),
+        }
+    }
+}
+
+impl std::error::Error for HeadlessError {}
+
+impl From<LayerError> for HeadlessError {
+    fn from(error: LayerError) -> Self {
+        HeadlessError::Layers(error)
+    }
+}
+
+impl From<ImageError> for HeadlessError {
+    fn from(error: ImageError) -> Self {
+        HeadlessError::Image(error)
+    }
+}
+
+/// Lays out `text` at `width` and renders it with forma's CPU renderer, without
+/// any window or GPU. The image is `width * scale` pixels wide and as high as
+/// the text needs. `font_context` has to know every font the text uses.
+pub fn render_rgba(
+    text: RichText,
+    font_context: &mut FontContext,
+    width: f32,
+    scale: f32,
+    background: Color,
+) -> Result<RgbaImage, HeadlessError> {
+    let mut text = Text::new(text);
+    let transform = AffineTransform::new_scale(scale, scale);
+    let mut layers = LayerAllocator::new();
+    let mut composition = Composition::new();
+    let mut ctx = WidgetContext {
+        font_context,
+        transform: &transform,
+        layers: &mut layers,
+        bitmaps: &BitmapCache::new(),
+        clip: None,
+        viewport: None,
+    };
+    let size = text.layout(&mut ctx, Size::new(width, f32::INFINITY))?;
+    text.compose(&ctx, &mut composition, Duration::ZERO);
+
+    // forma can't render into an empty buffer
+    let pixel_width = ((width * scale).ceil() as usize).max(1);
+    let pixel_height = ((size.h * scale).ceil() as usize).max(1);
+    let mut buffer = vec![0u8; pixel_width * pixel_height * 4];
+    let mut layout = LinearLayout::new(pixel_width, pixel_width * 4, pixel_height);
+    Renderer::new().render(
+        &mut composition,
+        &mut BufferBuilder::new(&mut buffer, &mut layout).build(),
+        RGBA,
+        background,
+        None,
+    );
+
+    Ok(
+        RgbaImage::from_raw(pixel_width as u32, pixel_height as u32, buffer)
+            .expect("the buffer has four bytes for every pixel"),
+    )
+}
+
+/// Renders `text` like `render_rgba` and writes it to `path` as a PNG
+pub fn render_png(
+    text: RichText,
+    font_context: &mut FontContext,
+    width: f32,
+    scale: f32,
+    background: Color,
+    path: impl AsRef<Path>,
+) -> Result<(), HeadlessError> {
+    let image = render_rgba(text, font_context, width, scale, background)?;
+    image.save_with_format(path, image::ImageFormat::Png)?;
+    Ok(())
+}
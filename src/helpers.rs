@@ -1,13 +1,29 @@
 use forma::prelude::{AffineTransform, Point};
 
+/// 2D affine math for forma's `AffineTransform`, which maps a point to
+/// `(ux * x + vx * y + tx, uy * x + vy * y + ty)`.
+///
+/// The `…ed` methods work in the transform's local space: the returned
+/// transform first applies the translation, scale, rotation or skew and then
+/// `self`, just like nesting a child inside a transformed parent.
 pub trait AffineHelpers: Sized {
     fn from_raw(raw: &[f32; 9]) -> Self;
     fn inverse(self) -> Option<Self>;
     fn transform_point(self, point: Point) -> Point;
     fn new_mirror(x: bool, y: bool) -> Self;
     fn translat(x: f32, y: f32) -> Self;
+    fn new_scale(x: f32, y: f32) -> Self;
+    fn new_rotation(angle: f32) -> Self;
+    /// Skews by the angles `x` along the x axis and `y` along the y axis
+    fn new_skew(x: f32, y: f32) -> Self;
+    /// The transform that applies `other` first and then `self`
+    fn concat(&self, other: &Self) -> Self;
     fn translated(&self, x: f32, y: f32) -> Self;
     fn scaled(&self, value: f32) -> Self;
+    /// Scales by `value` while `center`, in local space, stays in place
+    fn scaled_about(&self, value: f32, center: Point) -> Self;
+    fn rotated(&self, angle: f32) -> Self;
+    fn skewed(&self, x: f32, y: f32) -> Self;
     fn raw(&self) -> [f32; 9];
 }
 
@@ -47,27 +63,20 @@ impl AffineHelpers for AffineTransform {
             uy: -b * s,
             vx: -c * s,
             vy: a * s,
-            tx: (b * y - d * x) * s,
-            ty: (c * x - a * y) * s,
+            tx: (c * y - d * x) * s,
+            ty: (b * x - a * y) * s,
         })
     }
 
     fn transform_point(self, point: Point) -> Point {
         Point {
-            x: self.ux.mul_add(point.x, self.vx.mul_add(point.x, self.tx)),
-            y: self.uy.mul_add(point.y, self.vy.mul_add(point.y, self.ty)),
+            x: self.ux.mul_add(point.x, self.vx.mul_add(point.y, self.tx)),
+            y: self.uy.mul_add(point.x, self.vy.mul_add(point.y, self.ty)),
         }
     }
 
     fn new_mirror(x: bool, y: bool) -> Self {
-        AffineTransform {
-            ux: x.then(|| -1.0).unwrap_or(1.0),
-            uy: y.then(|| -1.0).unwrap_or(1.0),
-            vx: 0.0,
-            vy: 0.0,
-            tx: 0.0,
-            ty: 0.0,
-        }
+        Self::new_scale(if x { -1.0 } else { 1.0 }, if y { -1.0 } else { 1.0 })
     }
 
     fn translat(x: f32, y: f32) -> Self {
@@ -81,18 +90,71 @@ impl AffineHelpers for AffineTransform {
         }
     }
 
+    fn new_scale(x: f32, y: f32) -> Self {
+        AffineTransform {
+            ux: x,
+            uy: 0.0,
+            vx: 0.0,
+            vy: y,
+            tx: 0.0,
+            ty: 0.0,
+        }
+    }
+
+    fn new_rotation(angle: f32) -> Self {
+        let (sin, cos) = angle.sin_cos();
+        AffineTransform {
+            ux: cos,
+            uy: sin,
+            vx: -sin,
+            vy: cos,
+            tx: 0.0,
+            ty: 0.0,
+        }
+    }
+
+    fn new_skew(x: f32, y: f32) -> Self {
+        AffineTransform {
+            ux: 1.0,
+            uy: y.tan(),
+            vx: x.tan(),
+            vy: 1.0,
+            tx: 0.0,
+            ty: 0.0,
+        }
+    }
+
+    fn concat(&self, other: &Self) -> Self {
+        AffineTransform {
+            ux: self.ux * other.ux + self.vx * other.uy,
+            uy: self.uy * other.ux + self.vy * other.uy,
+            vx: self.ux * other.vx + self.vx * other.vy,
+            vy: self.uy * other.vx + self.vy * other.vy,
+            tx: self.ux * other.tx + self.vx * other.ty + self.tx,
+            ty: self.uy * other.tx + self.vy * other.ty + self.ty,
+        }
+    }
+
     fn translated(&self, x: f32, y: f32) -> Self {
-        let mut copy = *self;
-        copy.tx += x * copy.ux;
-        copy.ty += y * copy.vy;
-        copy
+        self.concat(&Self::translat(x, y))
     }
 
     fn scaled(&self, value: f32) -> Self {
-        let mut copy = *self;
-        copy.ux += value;
-        copy.vy += value;
-        copy
+        self.concat(&Self::new_scale(value, value))
+    }
+
+    fn scaled_about(&self, value: f32, center: Point) -> Self {
+        self.translated(center.x, center.y)
+            .scaled(value)
+            .translated(-center.x, -center.y)
+    }
+
+    fn rotated(&self, angle: f32) -> Self {
+        self.concat(&Self::new_rotation(angle))
+    }
+
+    fn skewed(&self, x: f32, y: f32) -> Self {
+        self.concat(&Self::new_skew(x, y))
     }
 
     fn raw(&self) -> [f32; 9] {
@@ -0,0 +1,138 @@
+use std::fmt;
+use std::ops::Range;
+
+use forma::Order;
+
+/// A contiguous block of forma layers owned by one widget
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct LayerRange {
+    start: u32,
+    len: u32,
+}
+
+impl LayerRange {
+    /// The first order in the range
+    pub fn start(&self) -> u32 {
+        self.start
+    }
+
+    /// One past the last order in the range
+    pub fn end(&self) -> u32 {
+        self.start + self.len
+    }
+
+    pub fn len(&self) -> u32 {
+        self.len
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len == 0
+    }
+
+    /// The order of the layer `offset` layers into the range, if it is part of it
+    pub fn order(&self, offset: u32) -> Option<Order> {
+        if offset >= self.len {
+            return None;
+        }
+        Order::new(self.start + offset).ok()
+    }
+
+    /// All orders in the range
+    pub fn orders(&self) -> impl Iterator<Item = Order> {
+        (self.start..self.end()).filter_map(|order| Order::new(order).ok())
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum LayerError {
+    /// There is no free block of `requested` layers left below forma's order limit
+    OutOfLayers { requested: u32 },
+}
+
+impl fmt::Display for LayerError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            LayerError::OutOfLayers { requested } => {
+                write!(f, "no free range of {requested} forma layers left")
+            }
+        }
+    }
+}
+
+impl std::error::Error for LayerError {}
+
+/// Hands out contiguous ranges of forma layer orders to widgets.
+/// Ranges that are freed again are reused by later reservations.
+#[derive(Debug, Clone)]
+pub struct LayerAllocator {
+    /// Free blocks, sorted and never adjacent to each other
+    free: Vec<Range<u32>>,
+}
+
+impl Default for LayerAllocator {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl LayerAllocator {
+    /// An allocator for all orders forma supports
+    pub fn new() -> Self {
+        Self::with_range(0..Order::MAX.as_u32() + 1)
+    }
+
+    /// An allocator that only hands out orders in `range`. Orders outside of it
+    /// stay available for the application's own layers.
+    pub fn with_range(range: Range<u32>) -> Self {
+        let end = range.end.min(Order::MAX.as_u32() + 1);
+        let mut free = Vec::new();
+        if range.start < end {
+            free.push(range.start..end);
+        }
+        Self { free }
+    }
+
+    /// Reserves `count` consecutive layers
+    pub fn reserve(&mut self, count: u32) -> Result<LayerRange, LayerError> {
+        if count == 0 {
+            let start = self.free.first().map(|block| block.start).unwrap_or(0);
+            return Ok(LayerRange { start, len: 0 });
+        }
+        let index = self
+            .free
+            .iter()
+            .position(|block| block.end - block.start >= count)
+            .ok_or(LayerError::OutOfLayers { requested: count })?;
+        let block = &mut self.free[index];
+        let start = block.start;
+        block.start += count;
+        if block.start == block.end {
+            self.free.remove(index);
+        }
+        Ok(LayerRange { start, len: count })
+    }
+
+    /// Returns `range` to the allocator
+    pub fn free(&mut self, range: LayerRange) {
+        if range.is_empty() {
+            return;
+        }
+        let freed = range.start..range.end();
+        let index = self
+            .free
+            .iter()
+            .position(|block| block.start >= freed.end)
+            .unwrap_or(self.free.len());
+        self.free.insert(index, freed);
+
+        // merge with the neighbouring blocks
+        if index + 1 < self.free.len() && self.free[index].end == self.free[index + 1].start {
+            self.free[index].end = self.free[index + 1].end;
+            self.free.remove(index + 1);
+        }
+        if index > 0 && self.free[index - 1].end == self.free[index].start {
+            self.free[index - 1].end = self.free[index].end;
+            self.free.remove(index);
+        }
+    }
+}
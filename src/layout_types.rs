@@ -1,13 +1,24 @@
 use forma::prelude::*;
+use forma::styling::{GradientBuilder, GradientType, Image, Texture};
 use parley::{style::Brush, FontContext};
 
-use crate::types::Size;
+use crate::cache::BitmapCache;
+use crate::helpers::AffineHelpers;
+use crate::layers::{LayerAllocator, LayerError, LayerRange};
+use crate::types::{Rect, Size};
 
 use std::time::Duration;
 
-#[derive(Debug, Clone, PartialEq, Eq)]
+#[derive(Debug, Clone, PartialEq)]
 pub struct FormaBrush {
     pub fill: Fill,
+    /// Replaces `fill` with a fill placed relative to the text. `fill` is still
+    /// used where that can't be built, e.g. for a gradient without stops.
+    pub text_fill: Option<TextFill>,
+    /// An outline around the glyphs, usually set with `StyleProperty::Stroke`
+    pub stroke: Option<TextStroke>,
+    /// A copy of the glyphs below them, usually set with `StyleProperty::Shadow`
+    pub shadow: Option<TextShadow>,
 }
 
 impl Default for FormaBrush {
@@ -19,27 +30,195 @@ impl Default for FormaBrush {
                 b: 0.,
                 a: 1.,
             }),
+            text_fill: None,
+            stroke: None,
+            shadow: None,
         }
     }
 }
 
+/// The coordinates a `TextFill` is given in
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum FillSpace {
+    /// The text's layout space, with the origin at the top left of the text
+    #[default]
+    Layout,
+    /// The bounding box of the stretch of text using the brush, from 0, 0 at
+    /// its top left to 1, 1 at its bottom right. Spans several lines and
+    /// changes of font if the stretch does. Images are placed over every
+    /// glyph run instead.
+    Bounds,
+    /// The whole laid out text, from 0, 0 at its top left to 1, 1 at its
+    /// bottom right
+    Text,
+}
+
+/// A fill that follows the text under any transform instead of being fixed
+/// to the screen, so it sweeps across words and lines in one piece
+#[derive(Debug, Clone, PartialEq)]
+pub enum TextFill {
+    /// A gradient from `start` to `end`. For radial gradients `start` is the
+    /// center and `end` lies on the outermost circle.
+    Gradient {
+        kind: GradientType,
+        start: Point,
+        end: Point,
+        /// Colors and their positions from 0 to 1 along the gradient
+        stops: Vec<(Color, f32)>,
+        space: FillSpace,
+    },
+    /// An image stretched over `rect`. With `FillSpace::Bounds` a rect from
+    /// 0, 0 to 1, 1 covers exactly the glyph run.
+    Image {
+        image: Image,
+        rect: Rect,
+        space: FillSpace,
+    },
+}
+
+impl TextFill {
+    /// Whether the fill is placed over each glyph run rather than the stretch
+    /// of text using it
+    pub(crate) fn is_per_run(&self) -> bool {
+        matches!(
+            self,
+            TextFill::Image {
+                space: FillSpace::Bounds,
+                ..
+            }
+        )
+    }
+
+    /// The forma fill for glyphs covering `bounds` of a text covering `text`
+    /// (both in layout space) that are placed on screen with `transform`
+    pub(crate) fn fill(
+        &self,
+        transform: &AffineTransform,
+        bounds: &Rect,
+        text: &Rect,
+    ) -> Option<Fill> {
+        match self {
+            TextFill::Gradient {
+                kind,
+                start,
+                end,
+                stops,
+                space,
+            } => {
+                let start = transform.transform_point(space.to_layout(*start, bounds, text));
+                let end = transform.transform_point(space.to_layout(*end, bounds, text));
+                let mut builder = GradientBuilder::new(start, end);
+                builder.r#type(*kind);
+                for (color, stop) in stops {
+                    builder.color_with_stop(*color, *stop);
+                }
+                builder.build().map(Fill::Gradient)
+            }
+            TextFill::Image { image, rect, space } => {
+                let origin = space.to_layout(rect.origin, bounds, text);
+                let corner = space.to_layout(Point::new(rect.max_x(), rect.max_y()), bounds, text);
+                // like emoji bitmaps: from image pixels to the screen and back
+                let image_transform =
+                    transform
+                        .translated(origin.x, origin.y)
+                        .concat(&AffineTransform::new_scale(
+                            (corner.x - origin.x) / image.width() as f32,
+                            (corner.y - origin.y) / image.height() as f32,
+                        ));
+                Some(Fill::Texture(Texture {
+                    transform: image_transform.inverse()?,
+                    image: image.clone(),
+                }))
+            }
+        }
+    }
+}
+
+impl FillSpace {
+    /// `point` given in this space, in layout space
+    pub(crate) fn to_layout(self, point: Point, bounds: &Rect, text: &Rect) -> Point {
+        let within = |rect: &Rect| {
+            Point::new(
+                rect.origin.x + point.x * rect.size.w,
+                rect.origin.y + point.y * rect.size.h,
+            )
+        };
+        match self {
+            FillSpace::Layout => point,
+            FillSpace::Bounds => within(bounds),
+            FillSpace::Text => within(text),
+        }
+    }
+}
+
+/// How the outline of a stroke continues where two segments meet
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum StrokeJoin {
+    /// Sharp corners, cut off where they would reach far beyond the stroke
+    Miter,
+    #[default]
+    Round,
+    Bevel,
+}
+
+/// An outline drawn around outline glyphs. Color glyphs and emoji are drawn
+/// without it.
+#[derive(Debug, Clone, PartialEq)]
+pub struct TextStroke {
+    /// Width of the whole stroke. It is drawn centered on the glyph outline and
+    /// below the fill, so with `fill` only half of it shows.
+    pub width: f32,
+    pub join: StrokeJoin,
+    /// Whether the glyphs are filled as well, or only outlined
+    pub fill: bool,
+    /// The stroke's own stroke is ignored. Text fills are placed over the
+    /// area of the stroke.
+    pub brush: Box<FormaBrush>,
+}
+
+/// A copy of the glyphs, and their strokes, drawn below them. Emoji cast the
+/// shadow of their silhouette.
+#[derive(Debug, Clone, PartialEq)]
+pub struct TextShadow {
+    /// How far the shadow is moved from the glyphs, in layout units
+    pub offset: Point,
+    /// Emoji silhouettes take the brush's color if it is solid and are black
+    /// otherwise. The shadow's own stroke and shadow are ignored.
+    pub brush: Box<FormaBrush>,
+    /// How far the soft edge of the shadow reaches beyond the glyphs, in layout
+    /// units. Zero casts a hard shadow. The edge is approximated with several
+    /// expanded translucent copies, which only fade out for solid brushes.
+    pub spread: f32,
+}
+
 impl Brush for FormaBrush {}
 
 pub trait Widget {
-    fn layout<'a>(&mut self, ctx: &mut WidgetContext<'a>, proposed_size: Size) -> Size;
+    fn layout<'a>(
+        &mut self,
+        ctx: &mut WidgetContext<'a>,
+        proposed_size: Size,
+    ) -> Result<Size, LayerError>;
     fn compose<'a>(
         &mut self,
         ctx: &WidgetContext<'a>,
         composition: &mut Composition,
         elapsed: Duration,
     );
+    /// The layers the widget reserved during its last layout
+    fn layers(&self) -> Option<LayerRange>;
 }
 
 pub struct WidgetContext<'a> {
     pub font_context: &'a mut FontContext,
     pub transform: &'a AffineTransform,
-    pub index: &'a mut u32,
-    pub clip: bool,
+    pub layers: &'a mut LayerAllocator,
+    /// Color bitmaps of emoji, shared by all widgets drawn with the context
+    pub bitmaps: &'a BitmapCache,
+    /// Content outside of this screen space rectangle is clipped away
+    pub clip: Option<Rect>,
+    /// The visible area in screen space. Content outside of it isn't composed.
+    pub viewport: Option<Rect>,
 }
 
 /// Key for building a glyph cache
@@ -1,6 +1,14 @@
+pub mod cache;
 pub mod conversion;
+pub mod document;
+pub mod headless;
 pub mod helpers;
+mod json;
+pub mod layers;
 pub mod layout_types;
+pub mod raster;
 pub mod rich_text;
+pub mod snapshot;
+mod svg;
 pub mod text;
 pub mod types;
@@ -0,0 +1,144 @@
+use forma::prelude::*;
+use parley::swash::scale::{Render, ScaleContext, Source};
+use parley::swash::zeno::Format;
+use parley::Font;
+
+use crate::cache::MaskCache;
+use crate::conversion::convert_mask;
+use crate::layout_types::CacheKey;
+
+/// How `Text` turns glyphs into forma geometry
+#[derive(Debug, Clone, Copy, PartialEq, Default)]
+pub enum RasterMode {
+    /// Every glyph is drawn from its vector outline
+    #[default]
+    Outlines,
+    /// Runs whose effective pixel size (the font size under
+    /// `WidgetContext.transform`) is at most `max_pixel_size` are rasterized
+    /// with swash's hinted mask renderer and drawn as one texture per layer,
+    /// which holds the outlines of one style on the lines around the viewport.
+    /// Larger text, rotated or skewed text and non-solid brushes keep
+    /// using outlines.
+    Hybrid { max_pixel_size: f32 },
+}
+
+/// The outlines of one layer rasterized at one pixel size
+pub(crate) struct RunRaster {
+    /// The scale of the transform the raster was built for
+    pub scale: f32,
+    /// The first glyph's origin in layout space. The image is placed relative
+    /// to it.
+    pub origin: Point,
+    pub left: i32,
+    pub top: i32,
+    pub width: usize,
+    pub height: usize,
+    pub image: Image,
+}
+
+/// The uniform scale of `transform` if it neither rotates, skews nor mirrors.
+/// Only those transforms can be drawn with pixel aligned masks.
+pub(crate) fn pixel_scale(transform: &AffineTransform) -> Option<f32> {
+    let is_axis_aligned = transform.uy == 0. && transform.vx == 0.;
+    let is_uniform = (transform.ux - transform.vy).abs() <= f32::EPSILON * transform.ux.abs();
+    (is_axis_aligned && is_uniform && transform.ux > 0.).then_some(transform.ux)
+}
+
+/// How much `transform` scales areas, expressed as a linear factor. Used to
+/// find the pixel size glyphs end up at under rotation and skew as well.
+pub(crate) fn effective_scale(transform: &AffineTransform) -> f32 {
+    (transform.ux * transform.vy - transform.uy * transform.vx)
+        .abs()
+        .sqrt()
+}
+
+/// Rasterizes `glyphs` (glyph id and layout position) of one layer into a
+/// single texture filled with `color`.
+#[allow(clippy::too_many_arguments)]
+pub(crate) fn rasterize_run(
+    glyphs: &[(u16, Point)],
+    font: &Font,
+    font_size: f32,
+    scale: f32,
+    color: Color,
+    context: &mut ScaleContext,
+    masks: &mut MaskCache,
+) -> Option<RunRaster> {
+    let origin = glyphs.first()?.1;
+    let pixel_size = (font_size * scale).round().max(1.);
+    let font = font.as_ref();
+    let mut scaler = context.builder(font).size(pixel_size).hint(true).build();
+
+    let mut placed = Vec::with_capacity(glyphs.len());
+    let (mut min_x, mut min_y, mut max_x, mut max_y) = (i32::MAX, i32::MAX, i32::MIN, i32::MIN);
+    for (id, point) in glyphs {
+        let key = CacheKey {
+            font_id: font.key.value() as usize,
+            glyph_id: *id,
+            font_size: pixel_size as i32,
+        };
+        masks.insert_with(key, || {
+            Render::new(&[Source::Outline])
+                .format(Format::Alpha)
+                .render(&mut scaler, *id)
+                .and_then(convert_mask)
+        });
+        let Some(mask) = masks.get(&key) else {
+            continue;
+        };
+        // pixel positions are rounded so every glyph stays crisp
+        let x = ((point.x - origin.x) * scale).round() as i32 + mask.placement.left;
+        let y = ((point.y - origin.y) * scale).round() as i32 - mask.placement.top;
+        min_x = min_x.min(x);
+        min_y = min_y.min(y);
+        max_x = max_x.max(x + mask.placement.width as i32);
+        max_y = max_y.max(y + mask.placement.height as i32);
+        placed.push((key, x, y));
+    }
+    if min_x >= max_x || min_y >= max_y {
+        return None;
+    }
+
+    let width = (max_x - min_x) as usize;
+    let height = (max_y - min_y) as usize;
+    let mut coverage = vec![0u8; width * height];
+    for (key, x, y) in placed {
+        let Some(mask) = masks.get(&key) else {
+            continue;
+        };
+        let mask_width = mask.placement.width as usize;
+        for (row, line) in mask.coverage.chunks_exact(mask_width.max(1)).enumerate() {
+            let start = (y - min_y) as usize * width + row * width + (x - min_x) as usize;
+            for (target, value) in coverage[start..start + line.len()].iter_mut().zip(line) {
+                *target = (*target).max(*value);
+            }
+        }
+    }
+
+    let pixels: Vec<_> = coverage
+        .into_iter()
+        .map(|value| [color.r, color.g, color.b, color.a * value as f32 / 255.])
+        .collect();
+    let image = Image::from_linear_rgba(&pixels[..], width, height).ok()?;
+
+    Some(RunRaster {
+        scale,
+        origin,
+        left: min_x,
+        top: min_y,
+        width,
+        height,
+        image,
+    })
+}
+
+/// An axis aligned rectangle in screen space
+pub(crate) fn rect_path(x: f32, y: f32, w: f32, h: f32) -> Path {
+    let mut builder = forma::PathBuilder::new();
+    builder.move_to(Point::new(x, y));
+    builder.line_to(Point::new(x + w, y));
+    builder.line_to(Point::new(x + w, y + h));
+    builder.line_to(Point::new(x, y + h));
+    builder.line_to(Point::new(x, y));
+    builder.build()
+}
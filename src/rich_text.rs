@@ -1,4 +1,5 @@
-use crate::layout_types::FormaBrush;
+use crate::layout_types::{FormaBrush, StrokeJoin, TextShadow, TextStroke};
+use forma::math::Point;
 use parley::style::StyleProperty as ParleyStyleProperty;
 use parley::style::{FontFamily, FontStack};
 use parley::{FontContext, Layout, LayoutContext};
@@ -6,7 +7,7 @@ use std::ops::Range;
 
 /// Simplification over `parley::style::StyleProperty` to
 /// build Rich Text in a simpler manner. Less performant.
-#[derive(Debug)]
+#[derive(Debug, Clone)]
 pub struct RichText {
     defaults: Vec<StyleProperty>,
     stack: Vec<(Range<usize>, StyleProperty)>,
@@ -26,6 +27,20 @@ impl RichText {
         self.stack.len()
     }
 
+    /// The attributes with the byte ranges of the text they apply to
+    pub fn attributes(&self) -> &[(Range<usize>, StyleProperty)] {
+        &self.stack
+    }
+
+    /// Length of the text in bytes
+    pub fn len(&self) -> usize {
+        self.text.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.text.is_empty()
+    }
+
     pub fn slice(&self, range: Range<usize>) -> &str {
         &self.text[range]
     }
@@ -59,6 +74,48 @@ impl RichText {
     pub fn add_newline(&mut self) {
         self.text.push('\n');
     }
+
+    /// Splits the text at its newlines into one `RichText` per paragraph.
+    /// Every paragraph keeps the defaults and the attributes overlapping it.
+    pub fn split_paragraphs(&self) -> Vec<RichText> {
+        let mut order: Vec<_> = (0..self.stack.len()).collect();
+        order.sort_by_key(|index| self.stack[*index].0.start);
+        let mut next = 0;
+        let mut active: Vec<usize> = Vec::new();
+
+        let mut paragraphs = Vec::new();
+        let mut start = 0;
+        for text in self.text.split('\n') {
+            let end = start + text.len();
+            // an empty paragraph takes the attributes of its newline
+            let reach = end.max(start + 1);
+            while next < order.len() && self.stack[order[next]].0.start < reach {
+                active.push(order[next]);
+                next += 1;
+            }
+            active.retain(|index| self.stack[*index].0.end > start);
+
+            let mut defaults = self.defaults.clone();
+            let mut stack = Vec::with_capacity(active.len());
+            for index in active.iter() {
+                let (range, property) = &self.stack[*index];
+                if text.is_empty() {
+                    // there is no range to attach it to, but it still sets the line height
+                    defaults.push(property.clone());
+                } else {
+                    let clipped = range.start.max(start) - start..range.end.min(end) - start;
+                    stack.push((clipped, property.clone()));
+                }
+            }
+            paragraphs.push(RichText {
+                defaults,
+                stack,
+                text: text.to_string(),
+            });
+            start = end + 1;
+        }
+        paragraphs
+    }
 }
 
 #[derive(Debug, Clone)]
@@ -71,12 +128,29 @@ pub enum StyleProperty {
     Underline(bool),
     LineHeight(f32),
     LetterSpacing(f32),
+    /// Outlines glyphs with `brush`. Without `fill` only the outline is drawn.
+    /// See `TextStroke`.
+    Stroke {
+        width: f32,
+        brush: FormaBrush,
+        join: StrokeJoin,
+        fill: bool,
+    },
+    /// Draws the glyphs with `brush` below themselves, moved by `offset`.
+    /// See `TextShadow`.
+    Shadow {
+        offset: Point,
+        brush: FormaBrush,
+        spread: f32,
+    },
 }
 
 impl StyleProperty {
-    fn as_parley<'a>(&self) -> ParleyStyleProperty<'a, FormaBrush> {
+    /// The property parley resolves. Strokes and shadows have no parley
+    /// counterpart and are merged into the brushes instead.
+    fn as_parley<'a>(&self) -> Option<ParleyStyleProperty<'a, FormaBrush>> {
         use ParleyStyleProperty as Py;
-        match self {
+        Some(match self {
             StyleProperty::Font(font) => Py::FontStack(FontStack::Single(FontFamily::Named(font))),
             StyleProperty::FontSize(size) => Py::FontSize(*size),
             StyleProperty::FontStyle(style) => Py::FontStyle(*style),
@@ -85,6 +159,53 @@ impl StyleProperty {
             StyleProperty::Underline(underline) => Py::Underline(*underline),
             StyleProperty::LineHeight(line_height) => Py::LineHeight(*line_height),
             StyleProperty::LetterSpacing(spacing) => Py::LetterSpacing(*spacing),
+            StyleProperty::Stroke { .. } | StyleProperty::Shadow { .. } => return None,
+        })
+    }
+
+    /// Whether the property ends up in the brush
+    fn is_brush(&self) -> bool {
+        matches!(
+            self,
+            StyleProperty::Brush(_) | StyleProperty::Stroke { .. } | StyleProperty::Shadow { .. }
+        )
+    }
+
+    /// Applies the property to a brush resolved so far
+    fn apply_to(&self, brush: &mut FormaBrush) {
+        match self {
+            StyleProperty::Brush(own) => {
+                // a brush doesn't reset a stroke or shadow set separately
+                let (stroke, shadow) = (brush.stroke.take(), brush.shadow.take());
+                *brush = own.clone();
+                brush.stroke = brush.stroke.take().or(stroke);
+                brush.shadow = brush.shadow.take().or(shadow);
+            }
+            StyleProperty::Stroke {
+                width,
+                brush: stroke_brush,
+                join,
+                fill,
+            } => {
+                brush.stroke = Some(TextStroke {
+                    width: *width,
+                    join: *join,
+                    fill: *fill,
+                    brush: Box::new(stroke_brush.clone()),
+                });
+            }
+            StyleProperty::Shadow {
+                offset,
+                brush: shadow_brush,
+                spread,
+            } => {
+                brush.shadow = Some(TextShadow {
+                    offset: *offset,
+                    brush: Box::new(shadow_brush.clone()),
+                    spread: *spread,
+                });
+            }
+            _ => {}
         }
     }
 }
@@ -97,11 +218,78 @@ impl RichText {
     ) -> Layout<FormaBrush> {
         let mut layout_builder = layout_context.ranged_builder(font_context, &self.text, 1.0);
         for property in self.defaults.iter() {
-            layout_builder.push_default(&property.as_parley());
+            if let Some(property) = property.as_parley() {
+                layout_builder.push_default(&property);
+            }
         }
         for (range, property) in self.stack.iter() {
-            layout_builder.push(&property.as_parley(), range.clone());
+            if let Some(property) = property.as_parley() {
+                layout_builder.push(&property, range.clone());
+            }
+        }
+        // pushed last, so they replace the brushes above
+        for (range, brush) in self.merged_brushes() {
+            layout_builder.push(&ParleyStyleProperty::Brush(brush), range);
         }
         layout_builder.build()
     }
+
+    /// The brushes with strokes and shadows merged in, for every stretch of
+    /// text where they stay the same. Empty if there are neither, as parley
+    /// resolves the brushes alone.
+    fn merged_brushes(&self) -> Vec<(Range<usize>, FormaBrush)> {
+        let needs_merging = self
+            .defaults
+            .iter()
+            .chain(self.stack.iter().map(|(_, property)| property))
+            .any(|property| {
+                matches!(
+                    property,
+                    StyleProperty::Stroke { .. } | StyleProperty::Shadow { .. }
+                )
+            });
+        if !needs_merging {
+            return Vec::new();
+        }
+
+        let mut default = FormaBrush::default();
+        for property in self.defaults.iter() {
+            property.apply_to(&mut default);
+        }
+        let mut order: Vec<_> = (0..self.stack.len())
+            .filter(|index| self.stack[*index].1.is_brush())
+            .collect();
+        let mut breaks: Vec<usize> = order
+            .iter()
+            .flat_map(|index| [self.stack[*index].0.start, self.stack[*index].0.end])
+            .chain([0, self.text.len()])
+            .filter(|index| *index <= self.text.len())
+            .collect();
+        breaks.sort_unstable();
+        breaks.dedup();
+        order.sort_by_key(|index| self.stack[*index].0.start);
+        let mut next = 0;
+        // the properties covering the current stretch, in the order they were added
+        let mut active: Vec<usize> = Vec::new();
+
+        let mut brushes = Vec::with_capacity(breaks.len());
+        for stretch in breaks.windows(2) {
+            let (start, end) = (stretch[0], stretch[1]);
+            while next < order.len() && self.stack[order[next]].0.start <= start {
+                let at = active.partition_point(|index| *index < order[next]);
+                active.insert(at, order[next]);
+                next += 1;
+            }
+            // ranges end on a break, so they either cover the stretch or end before it
+            active.retain(|index| self.stack[*index].0.end >= end);
+
+            let mut brush = default.clone();
+            // later properties win, like in parley
+            for index in active.iter() {
+                self.stack[*index].1.apply_to(&mut brush);
+            }
+            brushes.push((start..end, brush));
+        }
+        brushes
+    }
 }
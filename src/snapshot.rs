@@ -0,0 +1,333 @@
+use std::fmt;
+use std::path::{Path, PathBuf};
+
+use forma::prelude::Color;
+use image::{ImageError, Rgba, RgbaImage};
+use parley::FontContext;
+
+use crate::headless::{render_rgba, HeadlessError};
+use crate::rich_text::RichText;
+
+/// Setting this environment variable makes `Snapshots::new` record the current
+/// renders as references instead of comparing against them
+pub const UPDATE_VAR: &str = "TTED_UPDATE_SNAPSHOTS";
+
+/// How far a render may drift from its reference before the comparison fails
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Tolerance {
+    /// Largest difference in any channel a pixel may have and still match
+    pub channel: u8,
+    /// Share of pixels, from 0 to 1, that may differ by more than `channel`
+    pub pixels: f32,
+}
+
+impl Default for Tolerance {
+    /// Allows for antialiasing noise, but no visible changes
+    fn default() -> Self {
+        Self {
+            channel: 8,
+            pixels: 0.001,
+        }
+    }
+}
+
+#[derive(Debug)]
+pub enum SnapshotError {
+    Headless(HeadlessError),
+    Image(ImageError),
+    /// There is no reference to compare with. The render was written to `new`.
+    Missing {
+        name: String,
+        new: PathBuf,
+    },
+    /// The render has a different size than its reference
+    Size {
+        name: String,
+        expected: (u32, u32),
+        actual: (u32, u32),
+    },
+    /// More pixels differ than the tolerance allows. `diff` highlights them.
+    Mismatch {
+        name: String,
+        differing: usize,
+        diff: PathBuf,
+    },
+    /// A text snapshot differs from its reference, first at `line`
+    Text {
+        name: String,
+        line: usize,
+        new: PathBuf,
+    },
+}
+
+impl fmt::Display for SnapshotError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            SnapshotError::Headless(error) => write!(f, "{error}"),
+            SnapshotError::Image(error) => write!(f, "could not read or write a snapshot: {error}"),
+            SnapshotError::Missing { name, new } => write!(
+                f,
+                "snapshot {name} has no reference, see {} and record it with {UPDATE_VAR}=1",
+                new.display()
+            ),
+            SnapshotError::Size {
+                name,
+                expected,
+                actual,
+            } => write!(
+                f,
+                "snapshot {name} is {}x{} instead of {}x{}",
+                actual.0, actual.1, expected.0, expected.1
+            ),
+            SnapshotError::Mismatch {
+                name,
+                differing,
+                diff,
+            } => write!(
+                f,
+                "snapshot {name} differs in {differing} pixels, see {}",
+                diff.display()
+            ),
+            SnapshotError::Text { name, line, new } => write!(
+                f,
+                "snapshot {name} differs from line {line} on, see {}",
+                new.display()
+            ),
+        }
+    }
+}
+
+impl std::error::Error for SnapshotError {}
+
+impl From<HeadlessError> for SnapshotError {
+    fn from(error: HeadlessError) -> Self {
+        SnapshotError::Headless(error)
+    }
+}
+
+impl From<ImageError> for SnapshotError {
+    fn from(error: ImageError) -> Self {
+        SnapshotError::Image(error)
+    }
+}
+
+/// The result of comparing two images of the same size
+pub struct Diff {
+    /// Number of pixels outside the tolerance
+    pub differing: usize,
+    /// The expected image faded out, with the differing pixels in red
+    pub image: RgbaImage,
+}
+
+/// Compares `actual` with `expected` pixel by pixel. Both have to be the same size.
+pub fn diff(expected: &RgbaImage, actual: &RgbaImage, channel: u8) -> Diff {
+    let mut differing = 0;
+    let mut image = RgbaImage::new(expected.width(), expected.height());
+    for ((target, expected), actual) in image
+        .pixels_mut()
+        .zip(expected.pixels())
+        .zip(actual.pixels())
+    {
+        let distance = expected
+            .0
+            .iter()
+            .zip(actual.0)
+            .map(|(a, b)| a.abs_diff(b))
+            .max()
+            .unwrap_or(0);
+        *target = if distance > channel {
+            differing += 1;
+            Rgba([255, 0, 0, 255])
+        } else {
+            let [r, g, b, _] = expected.0.map(|value| 191 + value / 4);
+            Rgba([r, g, b, 255])
+        };
+    }
+    Diff { differing, image }
+}
+
+/// Compares renders against reference PNGs in a directory. A missing reference
+/// is an error unless references are being updated, so a checkout without them
+/// can't pass. On a mismatch the render is written next to the reference as
+/// `<name>.new.png` and the differences as `<name>.diff.png`.
+pub struct Snapshots {
+    dir: PathBuf,
+    tolerance: Tolerance,
+    background: Color,
+    update: bool,
+}
+
+impl Snapshots {
+    pub fn new(dir: impl Into<PathBuf>) -> Self {
+        Self {
+            dir: dir.into(),
+            tolerance: Tolerance::default(),
+            background: Color {
+                r: 1.,
+                g: 1.,
+                b: 1.,
+                a: 1.,
+            },
+            update: std::env::var_os(UPDATE_VAR).is_some(),
+        }
+    }
+
+    /// Whether every comparison records the render as the new reference
+    pub fn set_update(&mut self, update: bool) {
+        self.update = update;
+    }
+
+    pub fn set_tolerance(&mut self, tolerance: Tolerance) {
+        self.tolerance = tolerance;
+    }
+
+    /// The color `compare_text` renders the text on, white by default
+    pub fn set_background(&mut self, background: Color) {
+        self.background = background;
+    }
+
+    /// Renders `text` headless and compares it with the reference `name`
+    pub fn compare_text(
+        &self,
+        name: &str,
+        text: RichText,
+        font_context: &mut FontContext,
+        width: f32,
+        scale: f32,
+    ) -> Result<(), SnapshotError> {
+        let image = render_rgba(text, font_context, width, scale, self.background)?;
+        self.compare_image(name, &image)
+    }
+
+    /// Compares an image rendered by other means with the reference `name`
+    pub fn compare_image(&self, name: &str, actual: &RgbaImage) -> Result<(), SnapshotError> {
+        let reference = self.path(name, "png");
+        let new = self.path(name, "new.png");
+        let diff_path = self.path(name, "diff.png");
+
+        if self.update {
+            std::fs::create_dir_all(&self.dir).map_err(ImageError::IoError)?;
+            actual.save(&reference)?;
+            remove_if_present(&new)?;
+            remove_if_present(&diff_path)?;
+            return Ok(());
+        }
+        if !reference.exists() {
+            std::fs::create_dir_all(&self.dir).map_err(ImageError::IoError)?;
+            actual.save(&new)?;
+            return Err(SnapshotError::Missing {
+                name: name.to_owned(),
+                new,
+            });
+        }
+
+        let expected = image::open(&reference)?.into_rgba8();
+        if expected.dimensions() != actual.dimensions() {
+            actual.save(&new)?;
+            return Err(SnapshotError::Size {
+                name: name.to_owned(),
+                expected: expected.dimensions(),
+                actual: actual.dimensions(),
+            });
+        }
+
+        let diff = diff(&expected, actual, self.tolerance.channel);
+        let allowed = (self.tolerance.pixels * (actual.width() * actual.height()) as f32) as usize;
+        if diff.differing > allowed {
+            actual.save(&new)?;
+            diff.image.save(&diff_path)?;
+            return Err(SnapshotError::Mismatch {
+                name: name.to_owned(),
+                differing: diff.differing,
+                diff: diff_path,
+            });
+        }
+        remove_if_present(&new)?;
+        remove_if_present(&diff_path)?;
+        Ok(())
+    }
+
+    /// Compares text output, such as `Text::layout_json`, with the reference
+    /// `<name>.json`. Mismatches are written to `<name>.new.json`.
+    pub fn compare_json(&self, name: &str, actual: &str) -> Result<(), SnapshotError> {
+        let reference = self.path(name, "json");
+        let new = self.path(name, "new.json");
+
+        if self.update {
+            std::fs::create_dir_all(&self.dir).map_err(ImageError::IoError)?;
+            std::fs::write(&reference, actual).map_err(ImageError::IoError)?;
+            remove_if_present(&new)?;
+            return Ok(());
+        }
+        if !reference.exists() {
+            std::fs::create_dir_all(&self.dir).map_err(ImageError::IoError)?;
+            std::fs::write(&new, actual).map_err(ImageError::IoError)?;
+            return Err(SnapshotError::Missing {
+                name: name.to_owned(),
+                new,
+            });
+        }
+
+        let expected = std::fs::read_to_string(&reference).map_err(ImageError::IoError)?;
+        let mut expected_lines = expected.lines();
+        let mut actual_lines = actual.lines();
+        let mut line = 1;
+        loop {
+            match (expected_lines.next(), actual_lines.next()) {
+                (None, None) => break,
+                (expected, actual) if expected == actual => line += 1,
+                _ => {
+                    std::fs::write(&new, actual).map_err(ImageError::IoError)?;
+                    return Err(SnapshotError::Text {
+                        name: name.to_owned(),
+                        line,
+                        new,
+                    });
+                }
+            }
+        }
+        remove_if_present(&new)?;
+        Ok(())
+    }
+
+    /// Like `compare_json`, but panics with a readable message on a mismatch
+    pub fn assert_json(&self, name: &str, actual: &str) {
+        if let Err(error) = self.compare_json(name, actual) {
+            panic!("{error}");
+        }
+    }
+
+    /// Like `compare_text`, but panics with a readable message on a mismatch
+    pub fn assert_text(
+        &self,
+        name: &str,
+        text: RichText,
+        font_context: &mut FontContext,
+        width: f32,
+        scale: f32,
+    ) {
+        if let Err(error) = self.compare_text(name, text, font_context, width, scale) {
+            panic!("{error}");
+        }
+    }
+
+    /// Like `compare_image`, but panics with a readable message on a mismatch
+    pub fn assert_image(&self, name: &str, actual: &RgbaImage) {
+        if let Err(error) = self.compare_image(name, actual) {
+            panic!("{error}");
+        }
+    }
+
+    fn path(&self, name: &str, extension: &str) -> PathBuf {
+        self.dir.join(format!("{name}.{extension}"))
+    }
+}
+
+fn remove_if_present(path: &Path) -> Result<(), ImageError> {
+    match std::fs::remove_file(path) {
+        Err(error) if error.kind() != std::io::ErrorKind::NotFound => {
+            Err(ImageError::IoError(error))
+        }
+        _ => Ok(()),
+    }
+}
@@ -0,0 +1,243 @@
+use std::fmt::Write;
+use std::io::Cursor;
+
+use forma::prelude::*;
+use forma::styling::GradientType;
+use parley::swash::scale::image::{Content, Image as SwashImage};
+use parley::swash::zeno::Command;
+
+use crate::conversion::{place_commands, Convert, PathSink};
+use crate::layout_types::TextFill;
+use crate::types::Rect;
+
+/// Collects the elements of an SVG document
+pub(crate) struct SvgWriter {
+    out: String,
+    /// Number of gradients and patterns defined so far, for their ids
+    defined: usize,
+}
+
+impl SvgWriter {
+    pub(crate) fn new(width: f32, height: f32) -> Self {
+        let mut out = String::new();
+        let _ = writeln!(
+            out,
+            r#"<svg xmlns="http://www.w3.org/2000/svg" width="{width}" height="{height}" viewBox="0 0 {width} {height}">"#
+        );
+        Self { out, defined: 0 }
+    }
+
+    /// A filled path with the attributes of `fill_attributes` or `define`.
+    /// Empty paths are skipped.
+    pub(crate) fn path(&mut self, data: &str, fill: &str) {
+        if data.is_empty() {
+            return;
+        }
+        let _ = writeln!(self.out, r#"  <path d="{data}"{fill}/>"#);
+    }
+
+    /// Defines `fill` as a gradient or pattern for glyphs covering `bounds` of
+    /// a text covering `text`, and returns the attributes that paint with it
+    pub(crate) fn define(&mut self, fill: &TextFill, bounds: &Rect, text: &Rect) -> String {
+        let id = format!("fill{}", self.defined);
+        self.defined += 1;
+        let _ = writeln!(self.out, "  <defs>");
+        match fill {
+            TextFill::Gradient {
+                kind,
+                start,
+                end,
+                stops,
+                space,
+            } => {
+                let start = space.to_layout(*start, bounds, text);
+                let end = space.to_layout(*end, bounds, text);
+                let element = match kind {
+                    GradientType::Linear => {
+                        let _ = writeln!(
+                            self.out,
+                            r#"    <linearGradient id="{id}" gradientUnits="userSpaceOnUse" x1="{}" y1="{}" x2="{}" y2="{}">"#,
+                            start.x, start.y, end.x, end.y
+                        );
+                        "linearGradient"
+                    }
+                    GradientType::Radial => {
+                        let radius = (end.x - start.x).hypot(end.y - start.y);
+                        let _ = writeln!(
+                            self.out,
+                            r#"    <radialGradient id="{id}" gradientUnits="userSpaceOnUse" cx="{}" cy="{}" r="{radius}">"#,
+                            start.x, start.y
+                        );
+                        "radialGradient"
+                    }
+                };
+                for (color, offset) in stops {
+                    let [red, green, blue] = color.convert();
+                    let _ = writeln!(
+                        self.out,
+                        r##"      <stop offset="{offset}" stop-color="#{red:02x}{green:02x}{blue:02x}" stop-opacity="{}"/>"##,
+                        color.a.clamp(0., 1.)
+                    );
+                }
+                let _ = writeln!(self.out, "    </{element}>");
+            }
+            TextFill::Image { image, rect, space } => {
+                let origin = space.to_layout(rect.origin, bounds, text);
+                let corner = space.to_layout(Point::new(rect.max_x(), rect.max_y()), bounds, text);
+                let (width, height) = (corner.x - origin.x, corner.y - origin.y);
+                if let Some(png) = texture_png(image) {
+                    let _ = writeln!(
+                        self.out,
+                        r#"    <pattern id="{id}" patternUnits="userSpaceOnUse" x="{}" y="{}" width="{width}" height="{height}">"#,
+                        origin.x, origin.y
+                    );
+                    let _ = writeln!(
+                        self.out,
+                        r#"      <image width="{width}" height="{height}" preserveAspectRatio="none" href="data:image/png;base64,{}"/>"#,
+                        base64(&png)
+                    );
+                    let _ = writeln!(self.out, "    </pattern>");
+                }
+            }
+        }
+        let _ = writeln!(self.out, "  </defs>");
+        format!(r#" fill="url(#{id})""#)
+    }
+
+    /// A color bitmap placed at `x`, `y` and stretched to `width` by `height`
+    pub(crate) fn image(&mut self, x: f32, y: f32, width: f32, height: f32, image: &SwashImage) {
+        if image.content != Content::Color {
+            return;
+        }
+        let Some(png) = encode_png(
+            image.placement.width,
+            image.placement.height,
+            image.data.clone(),
+        ) else {
+            return;
+        };
+        let _ = writeln!(
+            self.out,
+            r#"  <image x="{x}" y="{y}" width="{width}" height="{height}" preserveAspectRatio="none" href="data:image/png;base64,{}"/>"#,
+            base64(&png)
+        );
+    }
+
+    /// A forma image placed at `x`, `y` and stretched to `width` by `height`
+    pub(crate) fn texture(&mut self, x: f32, y: f32, width: f32, height: f32, image: &Image) {
+        let Some(png) = texture_png(image) else {
+            return;
+        };
+        let _ = writeln!(
+            self.out,
+            r#"  <image x="{x}" y="{y}" width="{width}" height="{height}" preserveAspectRatio="none" href="data:image/png;base64,{}"/>"#,
+            base64(&png)
+        );
+    }
+
+    pub(crate) fn finish(mut self) -> String {
+        self.out.push_str("</svg>\n");
+        self.out
+    }
+}
+
+/// SVG path data for the swash `commands`, placed by `transform` exactly like
+/// `convert_path` places them for forma
+pub(crate) fn svg_path_data(
+    commands: impl Iterator<Item = Command>,
+    transform: &AffineTransform,
+) -> String {
+    let mut data = SvgPathData(String::new());
+    place_commands(commands, transform, &mut data);
+    data.0
+}
+
+/// Path data as it goes into the `d` attribute
+struct SvgPathData(String);
+
+impl SvgPathData {
+    fn push(&mut self, command: char, points: &[Point]) {
+        self.0.push(command);
+        for p in points {
+            let _ = write!(self.0, " {} {}", p.x, p.y);
+        }
+    }
+}
+
+impl PathSink for SvgPathData {
+    fn move_to(&mut self, p: Point) {
+        self.push('M', &[p]);
+    }
+
+    fn line_to(&mut self, p: Point) {
+        self.push('L', &[p]);
+    }
+
+    fn quad_to(&mut self, p1: Point, p2: Point) {
+        self.push('Q', &[p1, p2]);
+    }
+
+    fn cubic_to(&mut self, p1: Point, p2: Point, p3: Point) {
+        self.push('C', &[p1, p2, p3]);
+    }
+
+    fn close(&mut self) {
+        self.push('Z', &[]);
+    }
+}
+
+/// The attributes that paint with `fill`. Gradients and textures that are
+/// not a `TextFill` are fixed to the screen and have no counterpart in layout
+/// space, so they are drawn black.
+pub(crate) fn fill_attributes(fill: &Fill) -> String {
+    let Fill::Solid(color) = fill else {
+        return r#" fill="black""#.to_owned();
+    };
+    let [red, green, blue] = color.convert();
+    let mut attributes = format!(r##" fill="#{red:02x}{green:02x}{blue:02x}""##);
+    if color.a < 1. {
+        let _ = write!(attributes, r#" fill-opacity="{}""#, color.a.max(0.));
+    }
+    attributes
+}
+
+/// `image` as a PNG. forma keeps its images in linear colors.
+fn texture_png(image: &Image) -> Option<Vec<u8>> {
+    let data: Vec<u8> = image
+        .data()
+        .iter()
+        .flat_map(|pixel| {
+            let [r, g, b, a] = pixel.map(|value| value.to_f32());
+            let [red, green, blue] = Color { r, g, b, a }.convert();
+            [red, green, blue, (a.clamp(0., 1.) * 255.).round() as u8]
+        })
+        .collect();
+    encode_png(image.width(), image.height(), data)
+}
+
+fn encode_png(width: u32, height: u32, rgba: Vec<u8>) -> Option<Vec<u8>> {
+    let buffer = image::RgbaImage::from_raw(width, height, rgba)?;
+    let mut png = Cursor::new(Vec::new());
+    buffer
+        .write_to(&mut png, image::ImageOutputFormat::Png)
+        .ok()?;
+    Some(png.into_inner())
+}
+
+fn base64(bytes: &[u8]) -> String {
+    const ALPHABET: &[u8; 64] = b"ABCDEFGHIJKLMNOPQRSTUVWXYZabcdefghijklmnopqrstuvwxyz0123456789+/";
+    let mut out = String::with_capacity(bytes.len().div_ceil(3) * 4);
+    for chunk in bytes.chunks(3) {
+        let value = ((chunk[0] as u32) << 16)
+            | chunk.get(1).map_or(0, |b| (*b as u32) << 8)
+            | chunk.get(2).map_or(0, |b| *b as u32);
+        for index in 0..4 {
+            if index <= chunk.len() {
+                out.push(ALPHABET[((value >> (18 - 6 * index)) & 0x3f) as usize] as char);
+            } else {
+                out.push('=');
+            }
+        }
+    }
+    out
+}
@@ -1,42 +1,399 @@
+use std::collections::HashMap;
+use std::ops::Range;
 use std::time::Duration;
 
-use crate::conversion::{convert_bounds, convert_path, Convert};
-use crate::helpers::{shift_raw_transform, AffineHelpers};
-use crate::layout_types::{Widget, WidgetContext};
+use crate::cache::{BitmapCache, MaskCache, SilhouetteKey};
+use crate::conversion::{
+    convert_path, convert_placement, convert_silhouette, stroke_commands, Convert,
+};
+use crate::helpers::AffineHelpers;
+use crate::json::Json;
+use crate::layers::{LayerAllocator, LayerError, LayerRange};
+use crate::layout_types::{
+    CacheKey, FormaBrush, StrokeJoin, TextFill, TextShadow, TextStroke, Widget, WidgetContext,
+};
+use crate::raster::{
+    effective_scale, pixel_scale, rasterize_run, rect_path, RasterMode, RunRaster,
+};
 use crate::rich_text::RichText;
-use crate::types::Size;
+use crate::svg::{fill_attributes, svg_path_data, SvgWriter};
+use crate::types::{Rect, Size};
 
-use emoji::lookup_by_glyph::lookup;
+use forma::math::GeomPresTransform;
 use forma::prelude::*;
+use parley::swash::scale::outline::Outline;
 use parley::swash::scale::ScaleContext;
 use parley::swash::scale::StrikeWith;
-use parley::swash::zeno::PathData;
+use parley::swash::zeno::{Command, PathData, Placement};
 
+/// The glyphs drawn into one forma layer. Outlines that share font, size and
+/// style are batched across runs and lines, textures get a layer of their own.
+/// Glyphs are kept in line order, so culling can put just the lines around the
+/// viewport into the layer.
 #[derive(Default)]
 struct GlyphRunCache {
+    /// Offset of the layer into the widget's `LayerRange`
     layer_id: u32,
+    /// The lines the glyphs are on, each with the index of its first glyph
+    lines: Vec<(u32, usize)>,
     glyphs: Vec<GlyphCache>,
+    /// The style of the outlines in this layer
+    style: Style,
+    /// Whether further runs may add outlines to this layer
+    batched: bool,
+    /// The glyphs' font, kept for rasterizing them after layout
+    font: Option<parley::Font>,
+    font_size: f32,
+    /// The index of the COLR layer the outline was taken from
+    color_layer: Option<usize>,
+    /// Replaces the style's fill when composing
+    text_fill: Option<TextFill>,
+    /// The stretch of text drawn with one brush the glyphs belong to
+    fill_group: usize,
+    /// The area the glyphs of the fill group take up in layout space, which
+    /// `FillSpace::Bounds` fills are placed in
+    bounds: Option<Rect>,
+    /// The stroke the layer draws the outlines of instead of the glyphs
+    stroke: Option<TextStroke>,
+    /// The shadow the layer draws a copy of the glyphs for instead, and which
+    /// of the shadow's copies it is
+    shadow: Option<(TextShadow, u32)>,
+    /// The raster of some of the glyphs, and which ones
+    raster: Option<(Range<usize>, RunRaster)>,
+    /// The base scale of the paths currently in the layer, if any
+    base: Option<f32>,
+    /// The glyphs currently in the layer
+    built: Range<usize>,
+}
+
+/// Vertical extent of a laid out line
+#[derive(Clone, Copy)]
+struct LineBounds {
+    top: f32,
+    baseline: f32,
+    bottom: f32,
+}
+
+/// A glyph run of the last layout as it was shaped, before its glyphs were
+/// sorted into layers
+struct RunLayout {
+    line: u32,
+    /// Byte range of the text the run's glyphs were shaped from
+    source: Range<usize>,
+    font_size: f32,
+    glyphs: Vec<RunGlyph>,
+}
+
+struct RunGlyph {
+    id: u16,
+    kind: &'static str,
+    point: Point,
+    /// Every layer the glyph is drawn into, from bottom to top
+    layers: Vec<u32>,
 }
 
 enum GlyphCache {
     Text {
+        id: u16,
         path: Path,
-        style: Style,
+        /// Byte range of the text the glyph was shaped from
+        source: Range<usize>,
         point: Point,
+        /// How far the outline is grown on every side, for the copies a shadow
+        /// is drawn with
+        grow: f32,
     },
     Bitmap {
+        id: u16,
         path: Path,
         image: Image,
-        height: f32,
+        /// Where the image sits relative to the glyph origin, in image pixels
+        placement: Placement,
+        /// The pixel size the image was rasterized at
+        strike: f32,
+        source: Range<usize>,
         point: Point,
     },
 }
 
+impl GlyphCache {
+    /// The path moved to its place in layout space, scaled by `base`
+    fn placed_path(&self, base: f32) -> Path {
+        let (GlyphCache::Text { path, point, .. } | GlyphCache::Bitmap { path, point, .. }) = self;
+        let geometry = AffineTransform {
+            ux: base,
+            uy: 0.0,
+            vx: 0.0,
+            vy: base,
+            tx: point.x * base,
+            ty: point.y * base,
+        };
+        path.transform(&geometry.raw())
+    }
+}
+
+impl GlyphRunCache {
+    /// The glyphs on `lines`
+    fn glyph_range(&self, lines: &Range<u32>) -> Range<usize> {
+        let first_glyph = |line: u32| {
+            let index = self.lines.partition_point(|(start, _)| *start < line);
+            self.lines
+                .get(index)
+                .map_or(self.glyphs.len(), |(_, glyph)| *glyph)
+        };
+        first_glyph(lines.start)..first_glyph(lines.end)
+    }
+
+    /// An empty batch for outlines of `font` at `font_size` drawn with
+    /// `brush`, or for the outlines of their `stroke`, drawn with the stroke's
+    /// brush
+    fn outline_batch(
+        font: &parley::Font,
+        font_size: f32,
+        brush: &FormaBrush,
+        stroke: Option<&TextStroke>,
+    ) -> Self {
+        let brush = stroke.map_or(brush, |stroke| &*stroke.brush);
+        Self {
+            style: Style {
+                fill: brush.fill.clone(),
+                ..Default::default()
+            },
+            batched: true,
+            font: Some(font.clone()),
+            font_size,
+            text_fill: brush.text_fill.clone(),
+            stroke: stroke.cloned(),
+            ..Default::default()
+        }
+    }
+
+    /// An empty batch for the `step`th of the copies `shadow` is drawn with
+    fn shadow_batch(font: &parley::Font, font_size: f32, shadow: &TextShadow, step: u32) -> Self {
+        let brush = &*shadow.brush;
+        // Copy `step` reaches `step + 1` steps out. Where `n` of the copies
+        // overlap they add up to `n / steps` of the brush's opacity, so the
+        // shadow fades out evenly towards the edge of its spread.
+        let steps = shadow_steps(shadow) as f32;
+        let fill = match &brush.fill {
+            Fill::Solid(color) => Fill::Solid(Color {
+                a: color.a / (steps - color.a * (steps - step as f32 - 1.)),
+                ..*color
+            }),
+            fill => fill.clone(),
+        };
+        Self {
+            style: Style {
+                fill,
+                ..Default::default()
+            },
+            batched: true,
+            font: Some(font.clone()),
+            font_size,
+            text_fill: brush.text_fill.clone(),
+            shadow: Some((shadow.clone(), step)),
+            ..Default::default()
+        }
+    }
+
+    fn accepts(&self, batch: &GlyphRunCache) -> bool {
+        self.batched
+            && self.font_size == batch.font_size
+            && self.style.fill == batch.style.fill
+            && self.text_fill == batch.text_fill
+            && (self.text_fill.is_none() || self.fill_group == batch.fill_group)
+            && self.stroke == batch.stroke
+            && self.shadow == batch.shadow
+            && self.font.as_ref().map(|own| own.as_ref().key.value())
+                == batch.font.as_ref().map(|font| font.as_ref().key.value())
+    }
+
+    /// Entries sharing this key place their text fills over the same bounds:
+    /// the fill group, and which of the glyphs, strokes or shadow copies they
+    /// draw
+    fn bounds_key(&self) -> (usize, bool, Option<u32>) {
+        (
+            self.fill_group,
+            self.stroke.is_some(),
+            self.shadow.as_ref().map(|(_, step)| *step),
+        )
+    }
+
+    /// Adds an outline glyph on `line` covering `area` in layout space. Lines
+    /// are laid out in order, so `line` is never before the last glyph's.
+    fn push_outline(&mut self, line: u32, glyph: GlyphCache, area: Rect) {
+        if self.lines.last().map(|(last, _)| *last) != Some(line) {
+            self.lines.push((line, self.glyphs.len()));
+        }
+        self.glyphs.push(glyph);
+        if self.text_fill.is_some() {
+            self.bounds = Some(match self.bounds {
+                Some(bounds) => bounds.union(&area),
+                None => area,
+            });
+        }
+    }
+
+    /// The glyphs `ahead` rasterized at `scale`, rebuilt whenever the scale
+    /// changes or the raster lacks some of the `needed` glyphs. Only layers
+    /// with a solid brush can be rasterized.
+    fn raster(
+        &mut self,
+        scale: f32,
+        needed: &Range<usize>,
+        ahead: &Range<usize>,
+        context: &mut ScaleContext,
+        masks: &mut MaskCache,
+    ) -> Option<&RunRaster> {
+        let is_built = self
+            .raster
+            .as_ref()
+            .is_some_and(|(built, raster)| raster.scale == scale && covers(built, needed));
+        if !is_built {
+            // the mask renderer only knows plain glyph outlines, and only
+            // solid colors
+            if self.color_layer.is_some()
+                || self.text_fill.is_some()
+                || self.stroke.is_some()
+                || self.shadow.is_some()
+            {
+                return None;
+            }
+            let font = self.font.as_ref()?;
+            let Fill::Solid(color) = self.style.fill else {
+                return None;
+            };
+            let mut glyphs = Vec::with_capacity(ahead.len());
+            for glyph in self.glyphs[ahead.clone()].iter() {
+                let GlyphCache::Text { id, point, .. } = glyph else {
+                    return None;
+                };
+                glyphs.push((*id, *point));
+            }
+            self.raster =
+                rasterize_run(&glyphs, font, self.font_size, scale, color, context, masks)
+                    .map(|raster| (ahead.clone(), raster));
+        }
+        self.raster.as_ref().map(|(_, raster)| raster)
+    }
+
+    /// Fetches larger (or smaller) strikes for the color bitmaps of this layer
+    /// once the effective pixel size has moved far enough away from the strike
+    /// they were rasterized at. The gap between the two thresholds keeps small
+    /// zoom changes from rasterizing the same glyph over and over.
+    fn update_strikes(&mut self, scale: f32, context: &mut ScaleContext, bitmaps: &BitmapCache) {
+        let Some(font) = self.font.as_ref() else {
+            return;
+        };
+        // silhouettes are blurred once at layout, which hides their strike
+        if self.shadow.is_some() {
+            return;
+        }
+        let font_size = self.font_size;
+        let pixel_size = font_size * scale;
+        for glyph in self.glyphs.iter_mut() {
+            let GlyphCache::Bitmap {
+                id,
+                path,
+                image,
+                placement,
+                strike,
+                ..
+            } = glyph
+            else {
+                continue;
+            };
+            if pixel_size <= *strike * STRIKE_GROW && pixel_size >= *strike * STRIKE_SHRINK {
+                continue;
+            }
+            let size = (pixel_size.ceil() as u32)
+                .next_power_of_two()
+                .clamp(1, MAX_STRIKE) as f32;
+            if size == *strike {
+                continue;
+            }
+            let font = font.as_ref();
+            let key = CacheKey {
+                font_id: font.key.value() as usize,
+                glyph_id: *id,
+                font_size: size as i32,
+            };
+            let mut scaler = context.builder(font).size(size).build();
+            // the cache remembers glyphs without a bitmap at this size as well,
+            // so they are not rasterized again every frame
+            let Some(bitmap) = bitmaps.get_or_insert_with(key, || {
+                scaler
+                    .scale_color_bitmap(*id, StrikeWith::BestFit)
+                    .and_then(|img| img.convert())
+            }) else {
+                continue;
+            };
+            // placements are rounded to whole pixels, so the quad follows the image
+            *path = convert_placement(
+                &bitmap.placement,
+                &AffineTransform::new_mirror(false, true).scaled(font_size / size),
+            );
+            *image = bitmap.image;
+            *placement = bitmap.placement;
+            *strike = size;
+            self.base = None;
+        }
+    }
+}
+
+/// A bitmap is re-rasterized once it is drawn more than this factor above its strike size
+const STRIKE_GROW: f32 = 1.5;
+/// ...or below this factor of its strike size
+const STRIKE_SHRINK: f32 = 1. / 3.;
+/// Upper bound for re-rasterized strikes, in pixels
+const MAX_STRIKE: u32 = 512;
+
+/// Number of expanded copies the soft edge of a shadow is made of
+const SHADOW_STEPS: u32 = 4;
+/// Upper bound for the blur of emoji silhouettes, in pixels
+const MAX_SILHOUETTE_BLUR: f32 = 64.;
+
+/// A glyph of a laid out `Text` as a forma path
+#[derive(Debug, Clone)]
+pub struct GlyphOutline {
+    pub glyph_id: u16,
+    /// The glyph origin on the baseline, in layout space
+    pub position: Point,
+    /// Byte range of the text the glyph was shaped from. Ligatures and
+    /// clusters share one range between several glyphs.
+    pub source: Range<usize>,
+    /// The outline, or for color bitmaps the quad the bitmap is drawn on
+    pub path: Path,
+    pub is_bitmap: bool,
+    /// Whether `path` is the area of the glyph's stroke rather than the glyph
+    pub is_stroke: bool,
+    /// Whether `path` is one of the copies the glyph's shadow is drawn with.
+    /// For color bitmaps it is the quad of the shadow's silhouette.
+    pub is_shadow: bool,
+}
+
 pub struct Text {
     text: RichText,
     cache: Vec<GlyphRunCache>,
+    lines: Vec<LineBounds>,
+    runs: Vec<RunLayout>,
     cached_size: Size,
     needs_layout: bool,
+    layers: Option<LayerRange>,
+    /// The scale glyph paths were last built at
+    base: Option<f32>,
+    /// Layers of previous layouts that still have to be removed from the composition
+    stale_layers: Vec<LayerRange>,
+    /// Stale layers that are gone from the composition and can be freed
+    released_layers: Vec<LayerRange>,
+    silhouettes: BitmapCache<SilhouetteKey>,
+    raster_mode: RasterMode,
+    scale_context: ScaleContext,
+    masks: MaskCache,
+    /// Number of layers after ours the glyphs clip, if they are a clip mask
+    mask: Option<u32>,
+    /// The base scale and lines the mask layer was last filled with
+    mask_built: Option<(f32, Range<u32>)>,
 }
 
 impl Text {
@@ -45,8 +402,41 @@ impl Text {
         Self {
             text,
             cache: Vec::with_capacity(capacity),
+            lines: Vec::new(),
+            runs: Vec::new(),
             cached_size: Size::ZERO,
             needs_layout: true,
+            layers: None,
+            base: None,
+            stale_layers: Vec::new(),
+            released_layers: Vec::new(),
+            silhouettes: BitmapCache::new(),
+            raster_mode: RasterMode::default(),
+            scale_context: ScaleContext::new(),
+            masks: MaskCache::new(),
+            mask: None,
+            mask_built: None,
+        }
+    }
+
+    /// Switch between pure vector outlines and rasterized masks for small text
+    pub fn set_raster_mode(&mut self, mode: RasterMode) {
+        self.raster_mode = mode;
+        for entry in self.cache.iter_mut() {
+            entry.raster = None;
+        }
+    }
+
+    /// With `Some(count)` the glyphs aren't drawn but clip the `count` layer
+    /// orders that follow the text's layers, so content drawn there shows
+    /// through the letterforms. That content has to set `Style::is_clipped`.
+    /// Color bitmaps clip with their whole quad. `WidgetContext.clip` is
+    /// ignored while the text is a mask, as forma doesn't nest clips.
+    pub fn set_clip_mask(&mut self, mask: Option<u32>) {
+        if self.mask != mask {
+            self.mask = mask;
+            // the mask needs a layer of its own
+            self.needs_layout = true;
         }
     }
 
@@ -54,15 +444,301 @@ impl Text {
         self.text = text;
         self.needs_layout = true;
         self.cache.clear();
+        self.runs.clear();
         self.cached_size = Size::ZERO;
+        self.stale_layers.extend(self.layers.take());
+    }
+
+    /// Removes every layer of this text from `composition` and gives them back
+    /// to the allocator. The text is laid out again if it is used afterwards.
+    pub fn detach(&mut self, composition: &mut Composition, layers: &mut LayerAllocator) {
+        self.stale_layers.extend(self.layers.take());
+        self.remove_stale_layers(composition);
+        for range in self.released_layers.drain(..) {
+            layers.free(range);
+        }
+        self.cache.clear();
+        self.runs.clear();
+        self.cached_size = Size::ZERO;
+        self.needs_layout = true;
+    }
+
+    /// Describes the result of the last layout as JSON: the lines with the
+    /// glyph runs shaped on them, every glyph with its position, whether it is
+    /// drawn as an outline, a color outline or a bitmap and the layers it is
+    /// drawn into, followed by those layers. Positions are in layout space and
+    /// independent of any transform, so the output only changes when the
+    /// layout does. Meant for snapshot tests and debugging.
+    pub fn layout_json(&self) -> String {
+        let lines = self
+            .lines
+            .iter()
+            .enumerate()
+            .map(|(index, line)| {
+                let runs = self
+                    .runs
+                    .iter()
+                    .filter(|run| run.line as usize == index)
+                    .map(|run| {
+                        let glyphs = run
+                            .glyphs
+                            .iter()
+                            .map(|glyph| {
+                                let layers = glyph
+                                    .layers
+                                    .iter()
+                                    .map(|layer| Json::Int(*layer as i64))
+                                    .collect();
+                                Json::Object(vec![
+                                    ("id", Json::Int(glyph.id as i64)),
+                                    ("kind", Json::String(glyph.kind.to_owned())),
+                                    ("x", Json::Number(glyph.point.x)),
+                                    ("y", Json::Number(glyph.point.y)),
+                                    ("layers", Json::Array(layers)),
+                                ])
+                            })
+                            .collect();
+                        Json::Object(vec![
+                            (
+                                "source",
+                                Json::Array(vec![
+                                    Json::Int(run.source.start as i64),
+                                    Json::Int(run.source.end as i64),
+                                ]),
+                            ),
+                            ("font_size", Json::Number(run.font_size)),
+                            ("glyphs", Json::Array(glyphs)),
+                        ])
+                    })
+                    .collect();
+                Json::Object(vec![
+                    ("top", Json::Number(line.top)),
+                    ("baseline", Json::Number(line.baseline)),
+                    ("bottom", Json::Number(line.bottom)),
+                    ("runs", Json::Array(runs)),
+                ])
+            })
+            .collect();
+        let layers = self
+            .cache
+            .iter()
+            .map(|entry| {
+                let kind = match entry.glyphs.first() {
+                    _ if entry.shadow.is_some() => "shadow",
+                    Some(GlyphCache::Bitmap { .. }) => "bitmap",
+                    _ if entry.stroke.is_some() => "stroke",
+                    _ if entry.batched => "outlines",
+                    _ => "color_outline",
+                };
+                let order = self
+                    .layers
+                    .and_then(|layers| layers.order(entry.layer_id))
+                    .map_or(Json::Null, |order| Json::Int(order.as_u32() as i64));
+                Json::Object(vec![
+                    ("layer", Json::Int(entry.layer_id as i64)),
+                    ("order", order),
+                    ("kind", Json::String(kind.to_owned())),
+                    ("batched", Json::Bool(entry.batched)),
+                    ("font_size", Json::Number(entry.font_size)),
+                ])
+            })
+            .collect();
+        Json::Object(vec![
+            (
+                "size",
+                Json::Object(vec![
+                    ("w", Json::Number(self.cached_size.w)),
+                    ("h", Json::Number(self.cached_size.h)),
+                ]),
+            ),
+            ("lines", Json::Array(lines)),
+            ("layers", Json::Array(layers)),
+        ])
+        .pretty()
+    }
+
+    /// Every glyph of the last layout, placed by `transform`, in text order.
+    /// COLR glyphs have one outline per color layer, stroked glyphs another
+    /// one for their stroke and shadowed glyphs one per shadow copy, ahead of
+    /// the glyph's own.
+    pub fn outlines(&self, transform: &AffineTransform) -> Vec<GlyphOutline> {
+        let mut outlines: Vec<_> = self
+            .cache
+            .iter()
+            .flat_map(|entry| entry.glyphs.iter().map(move |glyph| (entry, glyph)))
+            .map(|(entry, glyph)| {
+                let (id, path, source, point, is_bitmap) = match glyph {
+                    GlyphCache::Text {
+                        id,
+                        path,
+                        source,
+                        point,
+                        ..
+                    } => (id, path, source, point, false),
+                    GlyphCache::Bitmap {
+                        id,
+                        path,
+                        source,
+                        point,
+                        ..
+                    } => (id, path, source, point, true),
+                };
+                GlyphOutline {
+                    glyph_id: *id,
+                    position: *point,
+                    source: source.clone(),
+                    path: path.transform(&transform.translated(point.x, point.y).raw()),
+                    is_bitmap,
+                    is_stroke: entry.stroke.is_some(),
+                    is_shadow: entry.shadow.is_some(),
+                }
+            })
+            .collect();
+        // Glyphs are sorted into layers, which are in drawing order. The sort is
+        // stable, so shadows stay below and COLR layers keep their order.
+        outlines.sort_by_key(|outline| outline.source.start);
+        outlines
+    }
+
+    /// Writes the result of the last layout as an SVG document in layout space.
+    /// Outlines are built from the same swash commands as the forma paths and
+    /// filled with their brush color, or with a gradient or pattern for text
+    /// fills. Color bitmaps are embedded as PNG images. Shadows are drawn with
+    /// the same copies as on screen, below the glyphs.
+    pub fn to_svg(&self) -> String {
+        let mut svg = SvgWriter::new(self.cached_size.w, self.cached_size.h);
+        let mirror = AffineTransform::new_mirror(false, true);
+        let text_box = Rect::new(Point::new(0., 0.), self.cached_size);
+        let mut context = ScaleContext::new();
+        // the entries are in the order of their layers, so in drawing order
+        for entry in self.cache.iter() {
+            let Some(font) = entry.font.as_ref() else {
+                continue;
+            };
+            let font = font.as_ref();
+            let fill = match entry.text_fill.as_ref().zip(entry.bounds.as_ref()) {
+                Some((text_fill, bounds)) => svg.define(text_fill, bounds, &text_box),
+                None => fill_attributes(&entry.style.fill),
+            };
+            for glyph in entry.glyphs.iter() {
+                match glyph {
+                    GlyphCache::Text {
+                        id, point, grow, ..
+                    } => {
+                        let mut scaler = context.builder(font).size(entry.font_size).build();
+                        let transform = AffineTransform::translat(point.x, point.y).concat(&mirror);
+                        let data = match (entry.color_layer, &entry.shadow) {
+                            (Some(index), _) => {
+                                scaler.scale_color_outline(*id).and_then(|outline| {
+                                    outline.get(index).map(|layer| {
+                                        svg_path_data(layer.path().commands(), &transform)
+                                    })
+                                })
+                            }
+                            // the outline layout grew the copy from
+                            (None, Some(_)) => {
+                                let has_palette = scaler.has_color_outlines()
+                                    && font.color_palettes().next().is_some();
+                                let commands = match has_palette
+                                    .then(|| scaler.scale_color_outline(*id))
+                                    .flatten()
+                                {
+                                    Some(outline) => Some(silhouette_commands(&outline)),
+                                    None => scaler
+                                        .scale_outline(*id)
+                                        .map(|outline| outline.path().commands().collect()),
+                                };
+                                commands.map(|commands: Vec<Command>| {
+                                    svg_path_data(
+                                        grown_commands(&commands, *grow).into_iter(),
+                                        &transform,
+                                    )
+                                })
+                            }
+                            (None, None) => {
+                                scaler
+                                    .scale_outline(*id)
+                                    .map(|outline| match &entry.stroke {
+                                        Some(stroke) => svg_path_data(
+                                            stroke_commands(
+                                                outline.path(),
+                                                stroke.width,
+                                                stroke.join,
+                                            )
+                                            .into_iter(),
+                                            &transform,
+                                        ),
+                                        None => {
+                                            svg_path_data(outline.path().commands(), &transform)
+                                        }
+                                    })
+                            }
+                        };
+                        if let Some(data) = data {
+                            svg.path(&data, &fill);
+                        }
+                    }
+                    GlyphCache::Bitmap {
+                        id,
+                        image,
+                        placement,
+                        strike,
+                        point,
+                        ..
+                    } => {
+                        // same placement as the texture in `compose`
+                        let scale = entry.font_size / strike;
+                        let (x, y) = (
+                            point.x + placement.left as f32 * scale,
+                            point.y - placement.top as f32 * scale,
+                        );
+                        let (width, height) = (
+                            placement.width as f32 * scale,
+                            placement.height as f32 * scale,
+                        );
+                        // a silhouette only exists as the blurred image
+                        if entry.shadow.is_some() {
+                            svg.texture(x, y, width, height, image);
+                            continue;
+                        }
+                        let mut scaler = context.builder(font).size(*strike).build();
+                        if let Some(bitmap) = scaler.scale_color_bitmap(*id, StrikeWith::BestFit) {
+                            svg.image(x, y, width, height, &bitmap);
+                        }
+                    }
+                }
+            }
+        }
+        svg.finish()
+    }
+
+    fn remove_stale_layers(&mut self, composition: &mut Composition) {
+        for range in self.stale_layers.drain(..) {
+            for order in range.orders() {
+                composition.remove(order);
+            }
+            self.released_layers.push(range);
+        }
     }
 }
 
 impl Widget for Text {
-    fn layout<'a>(&mut self, ctx: &mut WidgetContext<'a>, proposed_size: Size) -> Size {
+    fn layout<'a>(
+        &mut self,
+        ctx: &mut WidgetContext<'a>,
+        proposed_size: Size,
+    ) -> Result<Size, LayerError> {
+        // Layers are only freed once compose removed them, otherwise another
+        // widget could be handed a range that still shows our old glyphs
+        for range in self.released_layers.drain(..) {
+            ctx.layers.free(range);
+        }
         if !self.needs_layout {
-            return self.cached_size;
+            return Ok(self.cached_size);
         }
+        self.cache.clear();
+        self.lines.clear();
+        self.runs.clear();
         let mut layout_context = parley::LayoutContext::new();
         let mut layout = self.text.build(&mut layout_context, ctx.font_context);
         layout.break_all_lines(Some(proposed_size.w), parley::layout::Alignment::Start);
@@ -73,28 +749,78 @@ impl Widget for Text {
         let size = (layout.width(), layout.height()).into();
 
         let mut context = ScaleContext::new();
+        // the first layer of the range is the clip layer
+        let mut layer_count = 1;
+        // Consecutive runs with the same brush form a fill group, so a gradient
+        // sweeps across a whole stretch of text even where the font changes.
+        // Images are placed over every run of their own.
+        let mut fill_group = 0;
+        let mut group_brush: Option<FormaBrush> = None;
+        // the entries later runs may add outlines to
+        let mut batches = Vec::new();
 
-        for line in layout.lines() {
-            for glyph_run in line.glyph_runs() {
-                // each run needs a new layer as a run distinguishes colors (logic here can probably be simplified)
-                let layer_id = *ctx.index;
-                *ctx.index += 1;
-
-                let mut glyph_cache = GlyphRunCache {
-                    layer_id,
-                    ..Default::default()
-                };
+        for (line_index, line) in layout.lines().enumerate() {
+            let line_index = line_index as u32;
+            let metrics = line.metrics();
+            self.lines.push(LineBounds {
+                top: metrics.baseline - metrics.ascent - metrics.leading * 0.5,
+                baseline: metrics.baseline,
+                bottom: metrics.baseline + metrics.descent + metrics.leading * 0.5,
+            });
+            // the run the last glyph run belonged to and how many of its glyphs it used
+            let mut run_cursor: Option<(Range<usize>, usize)> = None;
 
+            for glyph_run in line.glyph_runs() {
                 let mut x = glyph_run.offset();
                 let y = glyph_run.baseline();
                 let run = glyph_run.run();
+
+                // The text each glyph was shaped from. A run is split into several
+                // glyph runs where its style changes, and those hand out its
+                // glyphs in visual order one after another.
+                let run_range = run.text_range();
+                let cursor = match run_cursor.take() {
+                    Some((range, cursor)) if range == run_range => cursor,
+                    _ => 0,
+                };
+                let mut sources = run
+                    .visual_clusters()
+                    .flat_map(|cluster| {
+                        let range = cluster.text_range();
+                        cluster.glyphs().map(move |_| range.clone())
+                    })
+                    .skip(cursor);
+                let mut consumed = 0;
                 let font = run.font().as_ref();
                 let font_size = run.font_size();
+
                 let style = glyph_run.style();
+                let is_per_run = [
+                    Some(&style.brush),
+                    style.brush.stroke.as_ref().map(|stroke| &*stroke.brush),
+                    style.brush.shadow.as_ref().map(|shadow| &*shadow.brush),
+                ]
+                .into_iter()
+                .flatten()
+                .any(|brush| brush.text_fill.as_ref().is_some_and(TextFill::is_per_run));
+                if is_per_run || group_brush.as_ref() != Some(&style.brush) {
+                    fill_group += 1;
+                    group_brush = Some(style.brush.clone());
+                }
+                let stroke = style.brush.stroke.as_ref();
+                let shadow = style.brush.shadow.as_ref();
+                // the layers the run's outlines and their strokes go to, looked
+                // up on the first outline
+                let mut batch = None;
+                // the layers of the shadow's copies, below all of the run's layers
+                let mut shadow_batches = None;
                 let vars: [(parley::swash::Tag, f32); 0] = [];
-
-                let range = run.text_range();
-                let slice = &self.text.slice(range);
+                let mut run_layout = RunLayout {
+                    line: line_index,
+                    source: usize::MAX..0,
+                    font_size,
+                    glyphs: Vec::new(),
+                };
 
                 let mut scaler = context
                     .builder(font)
@@ -104,54 +830,371 @@ impl Widget for Text {
                     .variations(vars)
                     .build();
 
-                for glyph in glyph_run.glyphs() {
-                    let is_emoji = lookup(slice).is_some();
+                // Glyphs are classified by the font itself: whatever it has a color
+                // bitmap for is drawn as a texture, everything else as an outline.
+                // This works per glyph, so runs mixing words and emoji are fine.
+                let has_color_bitmaps = scaler.has_color_bitmaps();
+                // COLR glyphs are drawn with the font's first CPAL palette
+                let palette = scaler
+                    .has_color_outlines()
+                    .then(|| font.color_palettes().next())
+                    .flatten();
 
-                    let Some(outline) = scaler.scale_outline(glyph.id) else {
-                                x += glyph.advance;
-                                continue
-                            };
-                    if let Some(image) = is_emoji
+                for glyph in glyph_run.glyphs() {
+                    let source = sources.next().unwrap_or_default();
+                    consumed += 1;
+                    run_layout.source.start = run_layout.source.start.min(source.start);
+                    run_layout.source.end = run_layout.source.end.max(source.end);
+                    let mut run_glyph = RunGlyph {
+                        id: glyph.id,
+                        kind: "none",
+                        point: Point::new(x, y),
+                        layers: Vec::new(),
+                    };
+                    if let Some(bitmap) = has_color_bitmaps
                         .then(|| {
-                            scaler
-                                .scale_color_bitmap(glyph.id, StrikeWith::BestFit)
-                                .and_then(|img| img.convert())
+                            let key = CacheKey {
+                                font_id: font.key.value() as usize,
+                                glyph_id: glyph.id,
+                                font_size: font_size.round() as i32,
+                            };
+                            ctx.bitmaps.get_or_insert_with(key, || {
+                                scaler
+                                    .scale_color_bitmap(glyph.id, StrikeWith::BestFit)
+                                    .and_then(|img| img.convert())
+                            })
                         })
                         .flatten()
                     {
-                        let bounds = outline.bounds();
-                        let path = convert_bounds(&bounds, &transform);
-
-                        glyph_cache.glyphs.push(GlyphCache::Bitmap {
-                            path,
-                            image,
-                            height: bounds.height(),
-                            point: Point::new(x, y),
+                        // the shadow is the emoji's silhouette, right below it
+                        if let Some(shadow) = shadow {
+                            let color = match shadow.brush.fill {
+                                Fill::Solid(color) => color,
+                                _ => Color {
+                                    r: 0.,
+                                    g: 0.,
+                                    b: 0.,
+                                    a: 1.,
+                                },
+                            };
+                            let blur = shadow.spread.clamp(0., MAX_SILHOUETTE_BLUR).round() as u32;
+                            let key = CacheKey {
+                                font_id: font.key.value() as usize,
+                                glyph_id: glyph.id,
+                                font_size: font_size.round() as i32,
+                            };
+                            let channels = [color.r, color.g, color.b, color.a].map(f32::to_bits);
+                            if let Some(silhouette) =
+                                self.silhouettes
+                                    .get_or_insert_with((key, blur, channels), || {
+                                        scaler
+                                            .scale_color_bitmap(glyph.id, StrikeWith::BestFit)
+                                            .and_then(|image| {
+                                                convert_silhouette(image, color, blur)
+                                            })
+                                    })
+                            {
+                                self.cache.push(GlyphRunCache {
+                                    layer_id: layer_count,
+                                    lines: vec![(line_index, 0)],
+                                    glyphs: vec![GlyphCache::Bitmap {
+                                        id: glyph.id,
+                                        path: convert_placement(&silhouette.placement, &transform),
+                                        image: silhouette.image,
+                                        placement: silhouette.placement,
+                                        strike: font_size,
+                                        source: source.clone(),
+                                        point: Point::new(x + shadow.offset.x, y + shadow.offset.y),
+                                    }],
+                                    font: Some(run.font().clone()),
+                                    font_size,
+                                    shadow: Some((shadow.clone(), 0)),
+                                    ..Default::default()
+                                });
+                                run_glyph.layers.push(layer_count);
+                                layer_count += 1;
+                            }
+                        }
+
+                        // bitmap fonts often have no outlines at all, so the quad
+                        // comes from the bitmap's own placement
+                        let path = convert_placement(&bitmap.placement, &transform);
+
+                        // forma props apply to a whole layer, so every texture
+                        // needs a layer of its own
+                        let layer_id = layer_count;
+                        layer_count += 1;
+                        run_glyph.kind = "bitmap";
+                        run_glyph.layers.push(layer_id);
+
+                        self.cache.push(GlyphRunCache {
+                            layer_id,
+                            lines: vec![(line_index, 0)],
+                            glyphs: vec![GlyphCache::Bitmap {
+                                id: glyph.id,
+                                path,
+                                image: bitmap.image,
+                                placement: bitmap.placement,
+                                strike: font_size,
+                                source,
+                                point: Point::new(x, y),
+                            }],
+                            font: Some(run.font().clone()),
+                            font_size,
+                            ..Default::default()
                         });
-                    } else {
-                        let path = convert_path(outline.path().commands(), &transform);
-
-                        glyph_cache.glyphs.push(GlyphCache::Text {
-                            path,
-                            style: Style {
-                                is_clipped: ctx.clip,
-                                fill: style.brush.fill.clone(),
+                    } else if let Some(color_outline) = palette
+                        .is_some()
+                        .then(|| scaler.scale_color_outline(glyph.id))
+                        .flatten()
+                    {
+                        let shadows: &[usize] = match shadow {
+                            Some(shadow) => shadow_batches.get_or_insert_with(|| {
+                                shadow_batch_indices(
+                                    &mut self.cache,
+                                    &mut layer_count,
+                                    &mut batches,
+                                    fill_group,
+                                    run.font(),
+                                    font_size,
+                                    shadow,
+                                )
+                            }),
+                            None => &[],
+                        };
+                        run_glyph.kind = "color_outline";
+                        run_glyph
+                            .layers
+                            .extend(shadows.iter().map(|index| self.cache[*index].layer_id));
+                        // the shadow is cast by all COLR layers together
+                        if let Some(shadow) = shadow {
+                            let point = Point::new(x + shadow.offset.x, y + shadow.offset.y);
+                            push_shadow(
+                                &mut self.cache,
+                                shadows,
+                                line_index,
+                                &silhouette_commands(&color_outline),
+                                0.,
+                                glyph_area(&self.lines, line_index, x, glyph.advance)
+                                    .offset(shadow.offset),
+                                &transform,
+                                |path, grow| GlyphCache::Text {
+                                    id: glyph.id,
+                                    path,
+                                    source: source.clone(),
+                                    point,
+                                    grow,
+                                },
+                            );
+                        }
+                        // Each COLR layer has its own color and the layers have to
+                        // be painted in order, so every one of them gets its own
+                        // forma layer. Layers without a palette index use the
+                        // run's brush.
+                        for index in 0..color_outline.len() {
+                            let Some(color_layer) = color_outline.get(index) else {
+                                continue;
+                            };
+                            let fill = match (color_layer.color_index(), palette) {
+                                (Some(color_index), Some(palette)) => {
+                                    Fill::Solid(palette.get(color_index).convert())
+                                }
+                                _ => style.brush.fill.clone(),
+                            };
+                            let path = convert_path(color_layer.path().commands(), &transform);
+
+                            let layer_id = layer_count;
+                            layer_count += 1;
+                            run_glyph.layers.push(layer_id);
+
+                            self.cache.push(GlyphRunCache {
+                                layer_id,
+                                lines: vec![(line_index, 0)],
+                                glyphs: vec![GlyphCache::Text {
+                                    id: glyph.id,
+                                    path,
+                                    source: source.clone(),
+                                    point: Point::new(x, y),
+                                    grow: 0.,
+                                }],
+                                style: Style {
+                                    fill,
+                                    ..Default::default()
+                                },
+                                font: Some(run.font().clone()),
+                                font_size,
+                                color_layer: Some(index),
                                 ..Default::default()
-                            },
-                            point: Point::new(x, y),
+                            });
+                        }
+                    } else if let Some(outline) = scaler.scale_outline(glyph.id) {
+                        let shadows: &[usize] = match shadow {
+                            Some(shadow) => shadow_batches.get_or_insert_with(|| {
+                                shadow_batch_indices(
+                                    &mut self.cache,
+                                    &mut layer_count,
+                                    &mut batches,
+                                    fill_group,
+                                    run.font(),
+                                    font_size,
+                                    shadow,
+                                )
+                            }),
+                            None => &[],
+                        };
+                        let (fill_index, stroke_index) = *batch.get_or_insert_with(|| {
+                            // shadows are drawn below strokes, and strokes below the fill
+                            let above = shadows
+                                .iter()
+                                .map(|index| self.cache[*index].layer_id)
+                                .max()
+                                .unwrap_or(0);
+                            let stroke_index = stroke.map(|stroke| {
+                                let batch = GlyphRunCache {
+                                    fill_group,
+                                    ..GlyphRunCache::outline_batch(
+                                        run.font(),
+                                        font_size,
+                                        &style.brush,
+                                        Some(stroke),
+                                    )
+                                };
+                                batch_index(
+                                    &mut self.cache,
+                                    &mut layer_count,
+                                    &mut batches,
+                                    batch,
+                                    above,
+                                )
+                            });
+                            let above =
+                                stroke_index.map_or(above, |index| self.cache[index].layer_id);
+                            let fill_index =
+                                stroke.map(|stroke| stroke.fill).unwrap_or(true).then(|| {
+                                    let batch = GlyphRunCache {
+                                        fill_group,
+                                        ..GlyphRunCache::outline_batch(
+                                            run.font(),
+                                            font_size,
+                                            &style.brush,
+                                            None,
+                                        )
+                                    };
+                                    batch_index(
+                                        &mut self.cache,
+                                        &mut layer_count,
+                                        &mut batches,
+                                        batch,
+                                        above,
+                                    )
+                                });
+                            (fill_index, stroke_index)
                         });
+
+                        run_glyph.kind = "outline";
+                        run_glyph.layers.extend(
+                            shadows
+                                .iter()
+                                .chain(&stroke_index)
+                                .chain(&fill_index)
+                                .map(|index| self.cache[*index].layer_id),
+                        );
+                        let area = glyph_area(&self.lines, line_index, x, glyph.advance);
+                        if let Some(shadow) = shadow {
+                            // the shadow covers the stroke as well
+                            let commands: Vec<Command> = outline.path().commands().collect();
+                            let point = Point::new(x + shadow.offset.x, y + shadow.offset.y);
+                            push_shadow(
+                                &mut self.cache,
+                                shadows,
+                                line_index,
+                                &commands,
+                                stroke.map_or(0., |stroke| stroke.width * 0.5),
+                                area.offset(shadow.offset),
+                                &transform,
+                                |path, grow| GlyphCache::Text {
+                                    id: glyph.id,
+                                    path,
+                                    source: source.clone(),
+                                    point,
+                                    grow,
+                                },
+                            );
+                        }
+                        if let Some(index) = fill_index {
+                            self.cache[index].push_outline(
+                                line_index,
+                                GlyphCache::Text {
+                                    id: glyph.id,
+                                    path: convert_path(outline.path().commands(), &transform),
+                                    source: source.clone(),
+                                    point: Point::new(x, y),
+                                    grow: 0.,
+                                },
+                                area,
+                            );
+                        }
+                        if let (Some(index), Some(stroke)) = (stroke_index, stroke) {
+                            let commands =
+                                stroke_commands(outline.path(), stroke.width, stroke.join);
+                            self.cache[index].push_outline(
+                                line_index,
+                                GlyphCache::Text {
+                                    id: glyph.id,
+                                    path: convert_path(commands.into_iter(), &transform),
+                                    source,
+                                    point: Point::new(x, y),
+                                    grow: 0.,
+                                },
+                                area.inflated(stroke.width * 0.5),
+                            );
+                        }
                     }
+                    run_layout.glyphs.push(run_glyph);
                     x += glyph.advance;
                 }
+                if run_layout.glyphs.is_empty() {
+                    run_layout.source = 0..0;
+                }
+                self.runs.push(run_layout);
+                run_cursor = Some((run_range, cursor + consumed));
+            }
+        }
 
-                self.cache.push(glyph_cache);
+        // `FillSpace::Bounds` fills span the glyphs of their whole fill group,
+        // across lines and batches
+        let mut group_bounds: HashMap<_, Rect> = HashMap::new();
+        for entry in self.cache.iter() {
+            if let Some(bounds) = entry.bounds {
+                group_bounds
+                    .entry(entry.bounds_key())
+                    .and_modify(|group| *group = group.union(&bounds))
+                    .or_insert(bounds);
+            }
+        }
+        for entry in self.cache.iter_mut() {
+            if entry.bounds.is_some() {
+                entry.bounds = group_bounds.get(&entry.bounds_key()).copied();
             }
         }
 
+        // the mask layer comes last, right before the layers it clips
+        if self.mask.is_some() {
+            layer_count += 1;
+        }
+        self.mask_built = None;
+
+        self.stale_layers.extend(self.layers.take());
+        self.layers = Some(ctx.layers.reserve(layer_count)?);
+
         self.cached_size = size;
         self.needs_layout = false;
 
-        size
+        Ok(size)
+    }
+
+    fn layers(&self) -> Option<LayerRange> {
+        self.layers
     }
 
     fn compose<'a>(
@@ -160,50 +1203,438 @@ impl Widget for Text {
         composition: &mut Composition,
         _elapsed: Duration,
     ) {
-        for entry in self.cache.iter() {
-            let layer = composition
-                .get_mut_or_insert_default(Order::new(entry.layer_id).unwrap())
-                .clear();
-            for glyph in entry.glyphs.iter() {
-                match glyph {
-                    GlyphCache::Text { path, style, point } => {
-                        let path_transform = ctx.transform.translated(point.x, point.y);
-                        let path = path.transform(&path_transform.raw());
-                        layer.insert(&path).set_props(Props {
+        self.compose_with_transform(ctx, ctx.transform, composition);
+    }
+}
+
+impl Text {
+    /// Composes the text placed with `transform` instead of the context's
+    /// transform. Used by containers that position texts themselves.
+    pub(crate) fn compose_with_transform(
+        &mut self,
+        ctx: &WidgetContext<'_>,
+        transform: &AffineTransform,
+        composition: &mut Composition,
+    ) {
+        let scale = match self.raster_mode {
+            RasterMode::Outlines => None,
+            RasterMode::Hybrid { .. } => pixel_scale(transform),
+        };
+        let bitmap_scale = effective_scale(transform);
+
+        self.remove_stale_layers(composition);
+
+        let Some(layers) = self.layers else {
+            return;
+        };
+
+        // The clip layer masks exactly the glyph layers that follow it in our range
+        let is_clipped = ctx.clip.is_some();
+        if let Some(order) = layers.order(0) {
+            let layer = composition.get_mut_or_insert_default(order);
+            match ctx.clip.filter(|_| self.mask.is_none()) {
+                Some(clip) => {
+                    layer
+                        .clear()
+                        .set_transform(GeomPresTransform::default())
+                        .insert(&rect_path(
+                            clip.origin.x,
+                            clip.origin.y,
+                            clip.size.w,
+                            clip.size.h,
+                        ))
+                        .set_props(Props {
                             fill_rule: FillRule::NonZero,
-                            func: Func::Draw(style.clone()),
-                        });
+                            func: Func::Clip(layers.len() as usize - 1),
+                        })
+                        .enable();
+                }
+                None => {
+                    layer.clear().disable();
+                }
+            }
+        }
+
+        // Glyph paths live in layout space scaled by `base` and are placed with a
+        // layer transform, so panning and most zooming only update that transform.
+        // forma only takes transforms that don't scale up, so the paths are
+        // rebuilt whenever the zoom leaves the range their base scale covers.
+        let base = base_scale(transform, self.base);
+        let Ok(layer_transform) = GeomPresTransform::try_from([
+            transform.ux / base,
+            transform.uy / base,
+            transform.vx / base,
+            transform.vy / base,
+            transform.tx,
+            transform.ty,
+        ]) else {
+            // A degenerate transform (zero scale, NaN) can't place the glyphs, so
+            // nothing is drawn rather than the glyphs somewhere else
+            self.hide_glyphs(composition, layers);
+            return;
+        };
+        self.base = Some(base);
+
+        // Only glyphs on lines in or around the viewport are drawn. The overscan
+        // around it keeps scrolling from rebuilding the layers on every frame.
+        let all_lines = 0..self.lines.len() as u32;
+        let (visible, overscan) = match ctx.viewport {
+            Some(viewport) => visible_lines(&self.lines, transform, &viewport),
+            None => (all_lines.clone(), all_lines),
+        };
+
+        if let Some(count) = self.mask {
+            self.compose_mask(composition, count, base, layer_transform, visible, overscan);
+            return;
+        }
+
+        // what `FillSpace::Text` fills are placed in
+        let text_box = Rect::new(Point::new(0., 0.), self.cached_size);
+
+        for entry in self.cache.iter_mut() {
+            let Some(order) = layers.order(entry.layer_id) else {
+                continue;
+            };
+            let layer = composition.get_mut_or_insert_default(order);
+
+            // lines in the overscan are drawn as well, so they are already in
+            // place when they scroll into view
+            let ahead = entry.glyph_range(&overscan);
+            if ahead.is_empty() {
+                layer.disable();
+                continue;
+            }
+            layer.enable();
+            // the layer is kept until the viewport reaches glyphs beyond it
+            let needed = entry.glyph_range(&visible);
+
+            entry.update_strikes(bitmap_scale, &mut self.scale_context, ctx.bitmaps);
+
+            if let (RasterMode::Hybrid { max_pixel_size }, Some(scale)) = (self.raster_mode, scale)
+            {
+                if entry.font_size * scale <= max_pixel_size {
+                    if let Some(raster) = entry.raster(
+                        scale,
+                        &needed,
+                        &ahead,
+                        &mut self.scale_context,
+                        &mut self.masks,
+                    ) {
+                        // snapping the origin keeps the mask on the pixel grid
+                        let origin = transform.transform_point(raster.origin);
+                        let x = origin.x.round() + raster.left as f32;
+                        let y = origin.y.round() + raster.top as f32;
+                        let path = rect_path(x, y, raster.width as f32, raster.height as f32);
+                        layer
+                            .clear()
+                            .set_transform(GeomPresTransform::default())
+                            .insert(&path)
+                            .set_props(Props {
+                                fill_rule: FillRule::NonZero,
+                                func: Func::Draw(Style {
+                                    is_clipped,
+                                    fill: Fill::Texture(forma::styling::Texture {
+                                        transform: AffineTransform::translat(-x, -y),
+                                        image: raster.image.clone(),
+                                    }),
+                                    ..Default::default()
+                                }),
+                            });
+                        // the layer holds screen space geometry now
+                        entry.base = None;
+                        continue;
                     }
-                    GlyphCache::Bitmap {
-                        path,
-                        image,
-                        height,
-                        point,
-                    } => {
-                        let path_transform = ctx.transform.translated(point.x, point.y);
-                        let path = path.transform(&path_transform.raw());
-                        let texture_transform = AffineTransform::from_raw(&shift_raw_transform(
-                            &path_transform.raw(),
-                            0.0,
-                            -height * ctx.transform.vy,
-                        ))
+                }
+            }
+
+            if entry.base != Some(base) || !covers(&entry.built, &needed) {
+                layer.clear();
+                for glyph in entry.glyphs[ahead.clone()].iter() {
+                    layer.insert(&glyph.placed_path(base));
+                }
+                entry.base = Some(base);
+                entry.built = ahead;
+            }
+            layer.set_transform(layer_transform);
+
+            match entry.glyphs.first() {
+                Some(GlyphCache::Text { .. }) => {
+                    // text fills are placed in layout space and follow the transform
+                    let fill = entry
+                        .text_fill
+                        .as_ref()
+                        .zip(entry.bounds.as_ref())
+                        .and_then(|(text_fill, bounds)| {
+                            text_fill.fill(transform, bounds, &text_box)
+                        })
+                        .unwrap_or_else(|| entry.style.fill.clone());
+                    layer.set_props(Props {
+                        fill_rule: FillRule::NonZero,
+                        func: Func::Draw(Style {
+                            is_clipped,
+                            fill,
+                            ..entry.style.clone()
+                        }),
+                    });
+                }
+                // Texture coordinates are in screen space, so unlike the path
+                // they have to follow every change of the transform
+                Some(GlyphCache::Bitmap {
+                    image,
+                    placement,
+                    strike,
+                    point,
+                    ..
+                }) => {
+                    // the image has `strike` pixels per em, the path `font_size` units
+                    let scale = entry.font_size / strike;
+                    let texture_transform = transform
+                        .translated(
+                            point.x + placement.left as f32 * scale,
+                            point.y - placement.top as f32 * scale,
+                        )
+                        .scaled(scale)
                         .inverse()
                         .unwrap_or_default();
 
-                        layer.insert(&path).set_props(Props {
-                            fill_rule: FillRule::NonZero,
-                            func: Func::Draw(Style {
-                                is_clipped: ctx.clip,
-                                fill: Fill::Texture(forma::styling::Texture {
-                                    transform: texture_transform,
-                                    image: image.clone(),
-                                }),
-                                ..Default::default()
+                    layer.set_props(Props {
+                        fill_rule: FillRule::NonZero,
+                        func: Func::Draw(Style {
+                            is_clipped,
+                            fill: Fill::Texture(forma::styling::Texture {
+                                transform: texture_transform,
+                                image: image.clone(),
                             }),
-                        });
-                    }
+                            ..Default::default()
+                        }),
+                    });
+                }
+                None => {}
+            }
+        }
+    }
+
+    /// Disables the glyph layers in `layers`. A clip mask is emptied instead,
+    /// so the content it clips stays hidden as well.
+    fn hide_glyphs(&mut self, composition: &mut Composition, layers: LayerRange) {
+        for entry in self.cache.iter() {
+            if let Some(order) = layers.order(entry.layer_id) {
+                composition.get_mut_or_insert_default(order).disable();
+            }
+        }
+        if self.mask.is_some() {
+            if let Some(order) = layers.order(layers.len().saturating_sub(1)) {
+                composition.get_mut_or_insert_default(order).clear();
+                self.mask_built = None;
+            }
+        }
+    }
+
+    /// Puts every visible glyph into the last layer of the range as one clip
+    fn compose_mask(
+        &mut self,
+        composition: &mut Composition,
+        count: u32,
+        base: f32,
+        layer_transform: GeomPresTransform,
+        visible: Range<u32>,
+        overscan: Range<u32>,
+    ) {
+        let Some(order) = self
+            .layers
+            .and_then(|layers| layers.order(layers.len().saturating_sub(1)))
+        else {
+            return;
+        };
+        let layer = composition.get_mut_or_insert_default(order);
+
+        let is_built = match &self.mask_built {
+            Some((built_base, lines)) => {
+                *built_base == base && visible.start >= lines.start && visible.end <= lines.end
+            }
+            None => false,
+        };
+        if !is_built {
+            layer.clear();
+            // a shadow has no place in a clip
+            for entry in self.cache.iter().filter(|entry| entry.shadow.is_none()) {
+                let built = entry.glyph_range(&overscan);
+                for glyph in entry.glyphs[built].iter() {
+                    layer.insert(&glyph.placed_path(base));
                 }
             }
+            self.mask_built = Some((base, overscan));
+        }
+        layer
+            .set_transform(layer_transform)
+            .set_props(Props {
+                fill_rule: FillRule::NonZero,
+                func: Func::Clip(count as usize),
+            })
+            .enable();
+    }
+}
+
+/// The lines inside `viewport`, and the lines within half a viewport around it,
+/// which are drawn ahead of time
+fn visible_lines(
+    lines: &[LineBounds],
+    transform: &AffineTransform,
+    viewport: &Rect,
+) -> (Range<u32>, Range<u32>) {
+    let Some(inverse) = transform.inverse() else {
+        return (0..0, 0..0);
+    };
+    // the viewport's vertical extent in layout space
+    let bounds = viewport.transformed(&inverse);
+    let (top, bottom) = (bounds.origin.y, bounds.max_y());
+    let overscan = (bottom - top) * 0.5;
+
+    let range = |top: f32, bottom: f32| {
+        let start = lines.partition_point(|line| line.bottom < top) as u32;
+        let end = lines.partition_point(|line| line.top <= bottom) as u32;
+        start..end.max(start)
+    };
+    (range(top, bottom), range(top - overscan, bottom + overscan))
+}
+
+/// The scale glyph paths are built at for `transform`. It is a power of two at
+/// least as large as the transform's largest axis scale, and is kept until the
+/// transform zooms in beyond it or out far enough to lose detail.
+fn base_scale(transform: &AffineTransform, current: Option<f32>) -> f32 {
+    // Rotation and skew spread an axis over both coordinates, so the bound
+    // covers the length of each axis as well as each row's reach
+    let scale = [
+        transform.ux.hypot(transform.uy),
+        transform.vx.hypot(transform.vy),
+        transform.ux.abs() + transform.vx.abs(),
+        transform.uy.abs() + transform.vy.abs(),
+    ]
+    .into_iter()
+    .fold(0f32, f32::max);
+    match current {
+        Some(base) if scale <= base && scale >= base * BASE_SHRINK => base,
+        _ => 2f32.powf(scale.max(f32::MIN_POSITIVE).log2().ceil()),
+    }
+}
+
+/// Glyph paths are rebuilt once they are drawn below this factor of their base scale
+const BASE_SHRINK: f32 = 0.25;
+
+/// The area a glyph at `x` with `advance` takes up on its line, in layout space
+fn glyph_area(lines: &[LineBounds], line: u32, x: f32, advance: f32) -> Rect {
+    let line = lines[line as usize];
+    Rect::new(
+        Point::new(x, line.top),
+        Size::new(advance, line.bottom - line.top),
+    )
+}
+
+/// Number of copies `shadow` is drawn with. Hard shadows need only one.
+fn shadow_steps(shadow: &TextShadow) -> u32 {
+    if shadow.spread > 0. {
+        SHADOW_STEPS
+    } else {
+        1
+    }
+}
+
+/// Indices of the batches in `fill_group` for the copies of `shadow`, added if
+/// there are none yet
+fn shadow_batch_indices(
+    cache: &mut Vec<GlyphRunCache>,
+    layer_count: &mut u32,
+    batches: &mut Vec<usize>,
+    fill_group: usize,
+    font: &parley::Font,
+    font_size: f32,
+    shadow: &TextShadow,
+) -> Vec<usize> {
+    (0..shadow_steps(shadow))
+        .map(|step| {
+            let batch = GlyphRunCache {
+                fill_group,
+                ..GlyphRunCache::shadow_batch(font, font_size, shadow, step)
+            };
+            batch_index(cache, layer_count, batches, batch, 0)
+        })
+        .collect()
+}
+
+/// Adds the copies of the outline `commands` on `line` that make up a shadow
+/// to the shadow's `batches`. Each copy is grown a step further towards the
+/// shadow's spread, on top of `extra`. `glyph` places a copy's path, grown by
+/// the amount it is given.
+#[allow(clippy::too_many_arguments)]
+fn push_shadow(
+    cache: &mut [GlyphRunCache],
+    batches: &[usize],
+    line: u32,
+    commands: &[Command],
+    extra: f32,
+    area: Rect,
+    transform: &AffineTransform,
+    glyph: impl Fn(Path, f32) -> GlyphCache,
+) {
+    for index in batches {
+        let entry = &mut cache[*index];
+        let Some((shadow, step)) = &entry.shadow else {
+            continue;
+        };
+        let grow = shadow.spread.max(0.) * (step + 1) as f32 / shadow_steps(shadow) as f32 + extra;
+        let path = convert_path(grown_commands(commands, grow).into_iter(), transform);
+        entry.push_outline(line, glyph(path, grow), area.inflated(grow));
+    }
+}
+
+/// `commands` grown by `grow` on every side: the outline fills the inside, a
+/// round stroke around it the grown edge
+fn grown_commands(commands: &[Command], grow: f32) -> Vec<Command> {
+    let mut grown = commands.to_vec();
+    if grow > 0. {
+        grown.extend(stroke_commands(commands, grow * 2., StrokeJoin::Round));
+    }
+    grown
+}
+
+/// The outlines of every layer of a COLR glyph, which together make up its
+/// silhouette
+fn silhouette_commands(outline: &Outline) -> Vec<Command> {
+    let mut commands = Vec::new();
+    for index in 0..outline.len() {
+        if let Some(layer) = outline.get(index) {
+            commands.extend(layer.path().commands());
         }
     }
+    commands
+}
+
+/// Index of a batch like `batch` whose layer is drawn above the layer `above`.
+/// `batch` is added with a layer of its own if none of the `batches` in `cache`
+/// accepts its glyphs yet.
+fn batch_index(
+    cache: &mut Vec<GlyphRunCache>,
+    layer_count: &mut u32,
+    batches: &mut Vec<usize>,
+    batch: GlyphRunCache,
+    above: u32,
+) -> usize {
+    if let Some(index) = batches.iter().copied().find(|index| {
+        let entry = &cache[*index];
+        entry.layer_id > above && entry.accepts(&batch)
+    }) {
+        return index;
+    }
+    cache.push(GlyphRunCache {
+        layer_id: *layer_count,
+        ..batch
+    });
+    *layer_count += 1;
+    batches.push(cache.len() - 1);
+    cache.len() - 1
+}
+
+/// Whether the glyphs `built` include the `needed` ones
+fn covers(built: &Range<usize>, needed: &Range<usize>) -> bool {
+    needed.is_empty() || (built.start <= needed.start && needed.end <= built.end)
 }
@@ -1,6 +1,8 @@
-use forma::math::Point;
+use forma::math::{AffineTransform, Point};
 
-#[derive(Debug, Copy, Clone)]
+use crate::helpers::AffineHelpers;
+
+#[derive(Debug, Copy, Clone, PartialEq)]
 pub struct Size {
     pub w: f32,
     pub h: f32,
@@ -23,8 +25,75 @@ impl Size {
     }
 }
 
-#[derive(Debug, Copy, Clone)]
+#[derive(Debug, Copy, Clone, PartialEq)]
 pub struct Rect {
     pub origin: Point,
     pub size: Size,
 }
+
+impl Rect {
+    pub const fn new(origin: Point, size: Size) -> Self {
+        Self { origin, size }
+    }
+
+    pub fn max_x(&self) -> f32 {
+        self.origin.x + self.size.w
+    }
+
+    pub fn max_y(&self) -> f32 {
+        self.origin.y + self.size.h
+    }
+
+    /// The four corners, clockwise from the origin
+    pub fn corners(&self) -> [Point; 4] {
+        [
+            self.origin,
+            Point::new(self.max_x(), self.origin.y),
+            Point::new(self.max_x(), self.max_y()),
+            Point::new(self.origin.x, self.max_y()),
+        ]
+    }
+
+    /// The rect grown by `by` on every side
+    pub fn inflated(&self, by: f32) -> Rect {
+        Rect::new(
+            Point::new(self.origin.x - by, self.origin.y - by),
+            Size::new(self.size.w + 2. * by, self.size.h + 2. * by),
+        )
+    }
+
+    /// The rect moved by `by`
+    pub fn offset(&self, by: Point) -> Rect {
+        Rect::new(
+            Point::new(self.origin.x + by.x, self.origin.y + by.y),
+            self.size,
+        )
+    }
+
+    /// The smallest rectangle containing both
+    pub fn union(&self, other: &Rect) -> Rect {
+        let min = Point::new(
+            self.origin.x.min(other.origin.x),
+            self.origin.y.min(other.origin.y),
+        );
+        let max = Point::new(
+            self.max_x().max(other.max_x()),
+            self.max_y().max(other.max_y()),
+        );
+        Rect::new(min, Size::new(max.x - min.x, max.y - min.y))
+    }
+
+    /// The bounding box of the rectangle after applying `transform`
+    pub fn transformed(&self, transform: &AffineTransform) -> Rect {
+        let (mut min, mut max) = (
+            Point::new(f32::MAX, f32::MAX),
+            Point::new(f32::MIN, f32::MIN),
+        );
+        for corner in self.corners() {
+            let point = transform.transform_point(corner);
+            min = Point::new(min.x.min(point.x), min.y.min(point.y));
+            max = Point::new(max.x.max(point.x), max.y.max(point.y));
+        }
+        Rect::new(min, Size::new(max.x - min.x, max.y - min.y))
+    }
+}
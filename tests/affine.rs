@@ -0,0 +1,194 @@
+use std::f32::consts::FRAC_PI_2;
+
+use forma::prelude::{AffineTransform, Point};
+use proptest::prelude::*;
+
+use tted::helpers::AffineHelpers;
+
+fn close(a: f32, b: f32) -> bool {
+    // f32 rounding grows with the magnitude of the intermediate products
+    (a - b).abs() <= 1e-2 + 1e-3 * a.abs().max(b.abs())
+}
+
+fn close_points(a: Point, b: Point) -> bool {
+    close(a.x, b.x) && close(a.y, b.y)
+}
+
+fn close_transforms(a: &AffineTransform, b: &AffineTransform) -> bool {
+    a.raw().iter().zip(b.raw()).all(|(a, b)| close(*a, b))
+}
+
+fn coefficient() -> impl Strategy<Value = f32> {
+    -4f32..4.
+}
+
+fn transform() -> impl Strategy<Value = AffineTransform> {
+    (
+        coefficient(),
+        coefficient(),
+        coefficient(),
+        coefficient(),
+        -100f32..100.,
+        -100f32..100.,
+    )
+        .prop_map(|(ux, uy, vx, vy, tx, ty)| AffineTransform {
+            ux,
+            uy,
+            vx,
+            vy,
+            tx,
+            ty,
+        })
+}
+
+fn point() -> impl Strategy<Value = Point> {
+    (-100f32..100., -100f32..100.).prop_map(|(x, y)| Point::new(x, y))
+}
+
+fn angle() -> impl Strategy<Value = f32> {
+    -std::f32::consts::PI..std::f32::consts::PI
+}
+
+/// Applies the raw row major matrix, independent of `transform_point`
+fn apply_raw(raw: &[f32; 9], point: Point) -> Point {
+    Point::new(
+        raw[0] * point.x + raw[1] * point.y + raw[2],
+        raw[3] * point.x + raw[4] * point.y + raw[5],
+    )
+}
+
+fn determinant(t: &AffineTransform) -> f32 {
+    t.ux * t.vy - t.vx * t.uy
+}
+
+#[test]
+fn identity_keeps_points() {
+    let point = Point::new(3., -7.);
+    assert_eq!(AffineTransform::default().transform_point(point), point);
+}
+
+#[test]
+fn mirror_flips_the_axes() {
+    let point = Point::new(3., -7.);
+    let mirror = AffineTransform::new_mirror(false, true);
+    assert_eq!(mirror.transform_point(point), Point::new(3., 7.));
+    let mirror = AffineTransform::new_mirror(true, false);
+    assert_eq!(mirror.transform_point(point), Point::new(-3., -7.));
+}
+
+#[test]
+fn quarter_rotation_turns_x_into_y() {
+    let rotation = AffineTransform::new_rotation(FRAC_PI_2);
+    assert!(close_points(
+        rotation.transform_point(Point::new(1., 0.)),
+        Point::new(0., 1.)
+    ));
+    assert!(close_points(
+        rotation.transform_point(Point::new(0., 1.)),
+        Point::new(-1., 0.)
+    ));
+}
+
+#[test]
+fn skew_shifts_along_the_other_axis() {
+    let skew = AffineTransform::new_skew(std::f32::consts::FRAC_PI_4, 0.);
+    assert!(close_points(
+        skew.transform_point(Point::new(0., 2.)),
+        Point::new(2., 2.)
+    ));
+    assert!(close_points(
+        skew.transform_point(Point::new(2., 0.)),
+        Point::new(2., 0.)
+    ));
+}
+
+proptest! {
+    #[test]
+    fn transform_point_matches_the_matrix(t in transform(), p in point()) {
+        prop_assert!(close_points(t.transform_point(p), apply_raw(&t.raw(), p)));
+    }
+
+    #[test]
+    fn raw_round_trips(t in transform()) {
+        prop_assert_eq!(AffineTransform::from_raw(&t.raw()), t);
+    }
+
+    #[test]
+    fn concat_applies_other_first(a in transform(), b in transform(), p in point()) {
+        let expected = a.transform_point(b.transform_point(p));
+        prop_assert!(close_points(a.concat(&b).transform_point(p), expected));
+    }
+
+    #[test]
+    fn concat_is_associative(a in transform(), b in transform(), c in transform()) {
+        prop_assert!(close_transforms(
+            &a.concat(&b).concat(&c),
+            &a.concat(&b.concat(&c))
+        ));
+    }
+
+    #[test]
+    fn inverse_undoes_the_transform(t in transform(), p in point()) {
+        prop_assume!(determinant(&t).abs() > 0.5);
+        let inverse = t.inverse().unwrap();
+        prop_assert!(close_points(inverse.transform_point(t.transform_point(p)), p));
+        prop_assert!(close_transforms(&t.concat(&inverse), &AffineTransform::default()));
+    }
+
+    #[test]
+    fn singular_transforms_have_no_inverse(x in coefficient(), y in coefficient()) {
+        // the second column is twice the first, which is exact in f32
+        let t = AffineTransform { ux: x, uy: y, vx: x * 2., vy: y * 2., tx: 1., ty: 2. };
+        prop_assert!(t.inverse().is_none());
+    }
+
+    #[test]
+    fn translated_moves_in_local_space(t in transform(), p in point(), x in -100f32..100., y in -100f32..100.) {
+        let expected = t.transform_point(Point::new(p.x + x, p.y + y));
+        prop_assert!(close_points(t.translated(x, y).transform_point(p), expected));
+    }
+
+    #[test]
+    fn scaled_multiplies_in_local_space(t in transform(), p in point(), s in 0.01f32..10.) {
+        let expected = t.transform_point(Point::new(p.x * s, p.y * s));
+        prop_assert!(close_points(t.scaled(s).transform_point(p), expected));
+    }
+
+    #[test]
+    fn scaled_about_keeps_the_center(t in transform(), center in point(), s in 0.01f32..10.) {
+        let scaled = t.scaled_about(s, center);
+        prop_assert!(close_points(scaled.transform_point(center), t.transform_point(center)));
+    }
+
+    #[test]
+    fn rotation_keeps_distances(a in angle(), p in point(), q in point()) {
+        let rotation = AffineTransform::new_rotation(a);
+        let (rp, rq) = (rotation.transform_point(p), rotation.transform_point(q));
+        let before = (p.x - q.x).hypot(p.y - q.y);
+        let after = (rp.x - rq.x).hypot(rp.y - rq.y);
+        prop_assert!(close(before, after));
+    }
+
+    #[test]
+    fn rotations_add_up(t in transform(), a in angle(), b in angle()) {
+        prop_assert!(close_transforms(&t.rotated(a).rotated(b), &t.rotated(a + b)));
+        prop_assert!(close_transforms(&t.rotated(a).rotated(-a), &t));
+    }
+
+    #[test]
+    fn skew_keeps_areas(x in -1.2f32..1.2, p in point()) {
+        let skew = AffineTransform::new_skew(x, 0.);
+        prop_assert!(close(determinant(&skew), 1.));
+        // points on the x axis stay where they are
+        let on_axis = Point::new(p.x, 0.);
+        prop_assert!(close_points(skew.transform_point(on_axis), on_axis));
+    }
+
+    #[test]
+    fn skewed_matches_concat(t in transform(), x in -1.2f32..1.2, y in -1.2f32..1.2) {
+        prop_assert!(close_transforms(
+            &t.skewed(x, y),
+            &t.concat(&AffineTransform::new_skew(x, y))
+        ));
+    }
+}
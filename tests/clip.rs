@@ -0,0 +1,119 @@
+use forma::prelude::*;
+
+use tted::layers::LayerAllocator;
+use tted::rich_text::{RichText, StyleProperty};
+use tted::text::Text;
+use tted::types::{Rect, Size};
+
+mod common;
+use common::{compose_widget, is_background, render};
+
+const WIDTH: usize = 400;
+const HEIGHT: usize = 200;
+
+/// A block of text that covers the whole canvas
+fn filled_text() -> Text {
+    let mut text = RichText::new([StyleProperty::Font("Roboto"), StyleProperty::FontSize(40.)]);
+    for _ in 0..6 {
+        text.add_str("HHHHHHHHHHHHHHHH");
+        text.add_newline();
+    }
+    Text::new(text)
+}
+
+/// Lays out and composes `widgets` in order, each with its own clip
+fn compose(widgets: &mut [(&mut Text, Option<Rect>)]) -> Vec<[u8; 4]> {
+    let mut layers = LayerAllocator::new();
+    let mut composition = Composition::new();
+    for (widget, clip) in widgets.iter_mut() {
+        compose_widget(
+            widget,
+            &mut composition,
+            &mut layers,
+            Size::new(WIDTH as f32, HEIGHT as f32),
+            &AffineTransform::default(),
+            *clip,
+            None,
+        );
+    }
+    render(&mut composition, WIDTH, HEIGHT)
+}
+
+fn pixel(pixels: &[[u8; 4]], x: usize, y: usize) -> [u8; 4] {
+    pixels[y * WIDTH + x]
+}
+
+/// Every pixel that is at least partly inside `rect`
+fn touches(rect: &Rect, x: usize, y: usize) -> bool {
+    let (x, y) = (x as f32, y as f32);
+    x + 1. > rect.origin.x && x < rect.max_x() && y + 1. > rect.origin.y && y < rect.max_y()
+}
+
+#[test]
+fn nothing_is_drawn_outside_the_clip() {
+    let clip = Rect::new(Point::new(100., 40.), Size::new(150., 80.));
+    let mut text = filled_text();
+    let pixels = compose(&mut [(&mut text, Some(clip))]);
+
+    for y in 0..HEIGHT {
+        for x in 0..WIDTH {
+            if !touches(&clip, x, y) {
+                assert!(
+                    is_background(pixel(&pixels, x, y)),
+                    "pixel {x}, {y} outside of the clip was drawn"
+                );
+            }
+        }
+    }
+    let inside = (40..120)
+        .flat_map(|y| (100..250).map(move |x| (x, y)))
+        .filter(|(x, y)| !is_background(pixel(&pixels, *x, *y)))
+        .count();
+    assert!(inside > 0, "no glyphs were drawn inside the clip");
+}
+
+#[test]
+fn clip_edges_between_pixels_leave_no_artifacts() {
+    let clip = Rect::new(Point::new(100.5, 40.5), Size::new(150., 80.));
+    let mut text = filled_text();
+    let pixels = compose(&mut [(&mut text, Some(clip))]);
+
+    // the rows and columns right next to the clip edges
+    for x in 0..WIDTH {
+        assert!(is_background(pixel(&pixels, x, 39)));
+        assert!(is_background(pixel(&pixels, x, 121)));
+    }
+    for y in 0..HEIGHT {
+        assert!(is_background(pixel(&pixels, 99, y)));
+        assert!(is_background(pixel(&pixels, 251, y)));
+    }
+}
+
+#[test]
+fn without_clip_the_text_covers_the_canvas() {
+    let mut text = filled_text();
+    let pixels = compose(&mut [(&mut text, None)]);
+
+    let outside = Rect::new(Point::new(100., 40.), Size::new(150., 80.));
+    let drawn = (0..HEIGHT)
+        .flat_map(|y| (0..WIDTH).map(move |x| (x, y)))
+        .filter(|(x, y)| !touches(&outside, *x, *y))
+        .filter(|(x, y)| !is_background(pixel(&pixels, *x, *y)))
+        .count();
+    assert!(drawn > 0);
+}
+
+#[test]
+fn clip_only_covers_its_own_layers() {
+    let clip = Rect::new(Point::new(100., 40.), Size::new(150., 80.));
+    let mut clipped = filled_text();
+    let mut unclipped = filled_text();
+    let pixels = compose(&mut [(&mut clipped, Some(clip)), (&mut unclipped, None)]);
+
+    let drawn = (0..HEIGHT)
+        .flat_map(|y| (0..WIDTH).map(move |x| (x, y)))
+        .filter(|(x, y)| !touches(&clip, *x, *y))
+        .filter(|(x, y)| !is_background(pixel(&pixels, *x, *y)))
+        .count();
+    assert!(drawn > 0, "the second text was clipped as well");
+}
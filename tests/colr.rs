@@ -0,0 +1,83 @@
+use forma::prelude::*;
+
+use tted::helpers::AffineHelpers;
+use tted::layout_types::FormaBrush;
+use tted::rich_text::{RichText, StyleProperty};
+use tted::text::Text;
+use tted::types::Size;
+
+mod common;
+use common::{color_in, compose_text, ink_bounds, render};
+
+const WIDTH: usize = 200;
+const HEIGHT: usize = 150;
+
+/// A blue "A" in the COLR test font at 100 px
+fn color_glyph() -> RichText {
+    let mut rich_text = RichText::new([
+        StyleProperty::Font("Colr Test"),
+        StyleProperty::FontSize(100.),
+        StyleProperty::Brush(FormaBrush {
+            fill: Fill::Solid(Color {
+                r: 0.,
+                g: 0.,
+                b: 1.,
+                a: 1.,
+            }),
+            ..Default::default()
+        }),
+    ]);
+    rich_text.add_str("A");
+    rich_text
+}
+
+fn compose(rich_text: RichText) -> (Text, Composition) {
+    compose_text(
+        rich_text,
+        Size::new(WIDTH as f32, HEIGHT as f32),
+        &AffineTransform::translat(10., 10.),
+    )
+}
+
+#[test]
+fn layers_take_the_palette_and_the_text_color() {
+    let (_, mut composition) = compose(color_glyph());
+    let pixels = render(&mut composition, WIDTH, HEIGHT);
+
+    // the 600 unit square is 60 px wide
+    let (left, _, right, _) = ink_bounds(&pixels, WIDTH).expect("nothing was drawn");
+    assert!((right - left).abs_diff(60) <= 1, "{left}..{right}");
+    let middle = (left + right) / 2;
+    let (red, blue) = color_in(&pixels, WIDTH, left..middle - 2);
+    assert!(red > blue * 4, "palette layer is {red} red, {blue} blue");
+    let (red, blue) = color_in(&pixels, WIDTH, middle + 2..right + 1);
+    assert!(blue > red * 4, "text color layer is {red} red, {blue} blue");
+}
+
+#[test]
+fn every_color_layer_gets_a_layer_of_its_own() {
+    let (text, _) = compose(color_glyph());
+
+    // one glyph in the layout, drawn with two layers
+    let json = text.layout_json();
+    assert_eq!(
+        json.matches(r#""kind": "color_outline""#).count(),
+        3,
+        "{json}"
+    );
+    let outlines = text.outlines(&AffineTransform::default());
+    assert_eq!(outlines.len(), 2);
+    assert!(outlines.iter().all(|outline| outline.glyph_id == 1));
+}
+
+#[test]
+fn svg_draws_the_layers_in_their_colors() {
+    let (text, _) = compose(color_glyph());
+
+    let svg = text.to_svg();
+    assert_eq!(svg.matches("<path ").count(), 2);
+    let red = svg.find(r##"fill="#ff0000""##).expect("no palette color");
+    let blue = svg.find(r##"fill="#0000ff""##).expect("no text color");
+    // the palette layer comes first, as in the font
+    assert!(red < blue);
+}
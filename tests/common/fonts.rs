@@ -0,0 +1,222 @@
+//! Minimal fonts built in memory, for the color glyph formats the bundled
+//! fonts don't have. Each maps `A` to glyph 1 and the letters after it to the
+//! glyphs that follow.
+
+use std::io::Cursor;
+
+/// "Colr Test": `A` is a 600 unit square whose left half is a red palette
+/// layer and whose right half is drawn in the text color
+pub fn colr_font() -> Vec<u8> {
+    let glyphs = [[0, 0, 600, 600], [0, 0, 300, 600], [300, 0, 600, 600]];
+    // glyph 1 is drawn as glyph 2 in palette color 0 and glyph 3 in the text color
+    let colr = u16s(&[0, 1, 0, 14, 0, 20, 2, 1, 0, 2, 2, 0, 3, 0xffff]);
+    // one palette with a single entry, opaque red as BGRA
+    let cpal = [u16s(&[0, 1, 1, 1, 0, 14, 0]), vec![0, 0, 255, 255]].concat();
+    build_font(
+        "Colr Test",
+        &glyphs,
+        1,
+        vec![(b"COLR", colr), (b"CPAL", cpal)],
+    )
+}
+
+/// The strikes of "Sbix Test" in pixels per em, each with its own color
+pub const STRIKES: [(u16, [u8; 4]); 4] = [
+    (16, [255, 0, 0, 255]),
+    (32, [0, 255, 0, 255]),
+    (64, [0, 0, 255, 255]),
+    (128, [255, 0, 255, 255]),
+];
+
+/// "Sbix Test": `A` is a square color bitmap filled with the color of its
+/// strike, at every strike of `STRIKES`. Its bottom right quarter is left
+/// transparent, which shows which way it is turned. It sits on the baseline
+/// and is five eighths of an em wide, so it fits into the glyph's advance.
+/// `B` is a plain outline without a bitmap, like the letters of an emoji font.
+pub fn sbix_font() -> Vec<u8> {
+    let header = 8 + 4 * STRIKES.len() as u32;
+    let mut sbix = u16s(&[1, 1]);
+    sbix.extend((STRIKES.len() as u32).to_be_bytes());
+    let mut data = Vec::new();
+    for (ppem, color) in STRIKES.iter() {
+        sbix.extend((header + data.len() as u32).to_be_bytes());
+        let size = *ppem as u32 * 5 / 8;
+        let mut png = Cursor::new(Vec::new());
+        image::RgbaImage::from_fn(size, size, |x, y| match x < size / 2 || y < size / 2 {
+            true => image::Rgba(*color),
+            false => image::Rgba([0; 4]),
+        })
+        .write_to(&mut png, image::ImageOutputFormat::Png)
+        .unwrap();
+        let png = png.into_inner();
+        // glyphs 0 and 2 have no bitmap, glyph 1 starts right after the four offsets
+        data.extend(u16s(&[*ppem, 72]));
+        let end = 20 + 8 + png.len() as u32;
+        for offset in [20, 20, end, end] {
+            data.extend(offset.to_be_bytes());
+        }
+        data.extend(i16s(&[0, 0]));
+        data.extend(b"png ");
+        data.extend(png);
+    }
+    sbix.extend(data);
+    let glyphs = [[0, 0, 600, 600], [100, 0, 500, 600]];
+    build_font("Sbix Test", &glyphs, 2, vec![(b"sbix", sbix)])
+}
+
+fn u16s(values: &[u16]) -> Vec<u8> {
+    values
+        .iter()
+        .flat_map(|value| value.to_be_bytes())
+        .collect()
+}
+
+fn i16s(values: &[i16]) -> Vec<u8> {
+    values
+        .iter()
+        .flat_map(|value| value.to_be_bytes())
+        .collect()
+}
+
+/// A glyph with one rectangle, wound clockwise like TrueType expects
+fn rect([x0, y0, x1, y1]: [i16; 4]) -> Vec<u8> {
+    let header = [1, x0, y0, x1, y1, 3, 0];
+    // four on-curve points given as deltas
+    let flags = [0x0101, 0x0101];
+    let xs = [x0, 0, x1 - x0, 0];
+    let ys = [y0, y1 - y0, 0, y0 - y1];
+    i16s(&[&header[..], &flags, &xs, &ys].concat())
+}
+
+/// A TrueType font named `family` with an empty glyph 0 followed by one
+/// rectangle per entry of `rects`, plus the `extra` tables. The first `mapped`
+/// of the rectangles are the glyphs of the letters from `A` on.
+fn build_font(
+    family: &str,
+    rects: &[[i16; 4]],
+    mapped: i16,
+    extra: Vec<(&[u8; 4], Vec<u8>)>,
+) -> Vec<u8> {
+    let last = 0x40 + mapped;
+    let glyphs: Vec<_> = [Vec::new()]
+        .into_iter()
+        .chain(rects.iter().copied().map(rect))
+        .collect();
+    let count = glyphs.len() as u16;
+    let mut loca = vec![0u32];
+    for glyph in glyphs.iter() {
+        loca.push(loca.last().unwrap() + glyph.len() as u32);
+    }
+    let hmtx: Vec<_> = [0]
+        .into_iter()
+        .chain(rects.iter().map(|rect| rect[0]))
+        .flat_map(|lsb| i16s(&[700, lsb]))
+        .collect();
+
+    let postscript = format!("{}-Regular", family.replace(' ', ""));
+    let mut name = u16s(&[0, 4, 6 + 4 * 12]);
+    let mut strings = Vec::new();
+    for (id, value) in [
+        (1, family),
+        (2, "Regular"),
+        (4, family),
+        (6, postscript.as_str()),
+    ] {
+        let encoded = u16s(&value.encode_utf16().collect::<Vec<_>>());
+        let (length, offset) = (encoded.len() as u16, strings.len() as u16);
+        name.extend(u16s(&[3, 1, 0x409, id, length, offset]));
+        strings.extend(encoded);
+    }
+    name.extend(strings);
+
+    // version 4 with the metrics of `hhea`, a regular weight and Basic Latin
+    let os2 = [
+        i16s(&[
+            4, 700, 400, 5, 0, 650, 600, 0, 75, 650, 600, 0, 350, 50, 300,
+        ]),
+        vec![0; 12],
+        i16s(&[0, 1, 0, 0, 0, 0, 0, 0]),
+        b"NONE".to_vec(),
+        i16s(&[0x40, 0x41, last, 800, -200, 0, 800, 200, 0, 1, 0, 0]),
+        i16s(&[500, 600, 0, 0x20, 0]),
+    ]
+    .concat();
+
+    // one format 4 subtable for the Unicode BMP, mapping `A` on to glyph 1 on
+    let cmap = [
+        u16s(&[0, 1, 3, 1, 0, 12]),
+        u16s(&[4, 32, 0, 4, 4, 1, 0]),
+        i16s(&[last, -1, 0, 0x41, -1, 1 - 0x41, 1, 0, 0]),
+    ]
+    .concat();
+
+    let mut tables: Vec<(&[u8; 4], Vec<u8>)> = vec![
+        (b"OS/2", os2),
+        (b"cmap", cmap),
+        (b"glyf", glyphs.concat()),
+        (
+            b"head",
+            [
+                u16s(&[1, 0, 1, 0, 0, 0, 0x5f0f, 0x3cf5, 3, 1000]),
+                vec![0; 16],
+                u16s(&[0, 0, 600, 600, 0, 8, 2, 1, 0]),
+            ]
+            .concat(),
+        ),
+        (
+            b"hhea",
+            [
+                i16s(&[1, 0, 800, -200, 0, 700, 0, 0, 600, 1, 0, 0, 0, 0, 0, 0, 0]),
+                u16s(&[count]),
+            ]
+            .concat(),
+        ),
+        (b"hmtx", hmtx),
+        (
+            b"loca",
+            loca.iter()
+                .flat_map(|offset| offset.to_be_bytes())
+                .collect(),
+        ),
+        (
+            b"maxp",
+            u16s(&[1, 0, count, 4, 1, 0, 0, 2, 0, 0, 0, 0, 0, 0, 0, 0]),
+        ),
+        (b"name", name),
+        (
+            b"post",
+            i16s(&[3, 0, 0, 0, -100, 50, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0]),
+        ),
+    ];
+    tables.extend(extra);
+    // the table directory is sorted by tag
+    tables.sort_by_key(|(tag, _)| **tag);
+
+    let mut font = u16s(&[1, 0, tables.len() as u16]);
+    let entry_selector = (tables.len() as u16).ilog2() as u16;
+    let search_range = 16 << entry_selector;
+    font.extend(u16s(&[
+        search_range,
+        entry_selector,
+        tables.len() as u16 * 16 - search_range,
+    ]));
+    let mut offset = 12 + 16 * tables.len() as u32;
+    for (tag, data) in tables.iter_mut() {
+        let length = data.len() as u32;
+        // every table starts on a four byte boundary
+        data.resize(data.len().next_multiple_of(4), 0);
+        let checksum = data
+            .chunks(4)
+            .map(|word| u32::from_be_bytes([word[0], word[1], word[2], word[3]]))
+            .fold(0u32, u32::wrapping_add);
+        font.extend_from_slice(*tag);
+        for value in [checksum, offset, length] {
+            font.extend(value.to_be_bytes());
+        }
+        offset += data.len() as u32;
+    }
+    for (_, data) in tables {
+        font.extend(data);
+    }
+    font
+}
@@ -0,0 +1,202 @@
+//! Helpers shared by the render tests
+#![allow(dead_code)]
+
+use std::ops::Range;
+use std::time::Duration;
+
+use forma::cpu::buffer::layout::LinearLayout;
+use forma::cpu::buffer::BufferBuilder;
+use forma::cpu::{Renderer, RGBA};
+use forma::prelude::*;
+use parley::FontContext;
+
+use tted::cache::BitmapCache;
+use tted::layers::LayerAllocator;
+use tted::layout_types::{FormaBrush, Widget, WidgetContext};
+use tted::rich_text::RichText;
+use tted::text::Text;
+use tted::types::{Rect, Size};
+
+pub mod fonts;
+
+/// Roboto, plus the color fonts of `fonts`
+pub fn font_context() -> FontContext {
+    let mut context = FontContext::new();
+    context.register_fonts(include_bytes!("../../assets/Roboto-Regular.ttf").to_vec());
+    context.register_fonts(fonts::colr_font());
+    context.register_fonts(fonts::sbix_font());
+    context
+}
+
+/// Lays out `rich_text` in `size` and composes it with `transform` into a
+/// composition of its own
+pub fn compose_text(
+    rich_text: RichText,
+    size: Size,
+    transform: &AffineTransform,
+) -> (Text, Composition) {
+    let mut text = Text::new(rich_text);
+    let mut composition = Composition::new();
+    compose_widget(
+        &mut text,
+        &mut composition,
+        &mut LayerAllocator::new(),
+        size,
+        transform,
+        None,
+        None,
+    );
+    (text, composition)
+}
+
+/// Lays out `text` in `size` and composes it with `transform` into
+/// `composition`, with its layers taken from `layers`
+pub fn compose_widget(
+    text: &mut Text,
+    composition: &mut Composition,
+    layers: &mut LayerAllocator,
+    size: Size,
+    transform: &AffineTransform,
+    clip: Option<Rect>,
+    viewport: Option<Rect>,
+) {
+    let mut font_context = font_context();
+    let mut ctx = WidgetContext {
+        font_context: &mut font_context,
+        transform,
+        layers,
+        bitmaps: &BitmapCache::new(),
+        clip,
+        viewport,
+    };
+    text.layout(&mut ctx, size).unwrap();
+    text.compose(&ctx, composition, Duration::ZERO);
+}
+
+/// A text that keeps its composition and layers from frame to frame, laid out
+/// as wide as the `width` by `height` image it is rendered into
+pub struct Scene {
+    pub text: Text,
+    pub composition: Composition,
+    pub layers: LayerAllocator,
+    width: usize,
+    height: usize,
+}
+
+impl Scene {
+    pub fn new(rich_text: RichText, width: usize, height: usize) -> Self {
+        Self {
+            text: Text::new(rich_text),
+            composition: Composition::new(),
+            layers: LayerAllocator::new(),
+            width,
+            height,
+        }
+    }
+
+    /// Lays out the text and composes it with `transform`, culled to `viewport`
+    pub fn compose(&mut self, transform: &AffineTransform, viewport: Option<Rect>) {
+        compose_widget(
+            &mut self.text,
+            &mut self.composition,
+            &mut self.layers,
+            Size::new(self.width as f32, self.height as f32),
+            transform,
+            None,
+            viewport,
+        );
+    }
+
+    /// Renders the composition as it was last composed
+    pub fn render(&mut self) -> Vec<[u8; 4]> {
+        render(&mut self.composition, self.width, self.height)
+    }
+
+    /// Composes the text like `compose` and renders it
+    pub fn show(&mut self, transform: &AffineTransform, viewport: Option<Rect>) -> Vec<[u8; 4]> {
+        self.compose(transform, viewport);
+        self.render()
+    }
+}
+
+/// Renders `composition` on a white background into RGBA pixels, row by row
+pub fn render(composition: &mut Composition, width: usize, height: usize) -> Vec<[u8; 4]> {
+    let mut buffer = vec![0u8; width * height * 4];
+    let mut layout = LinearLayout::new(width, width * 4, height);
+    Renderer::new().render(
+        composition,
+        &mut BufferBuilder::new(&mut buffer, &mut layout).build(),
+        RGBA,
+        Color {
+            r: 1.,
+            g: 1.,
+            b: 1.,
+            a: 1.,
+        },
+        None,
+    );
+    buffer
+        .chunks_exact(4)
+        .map(|pixel| [pixel[0], pixel[1], pixel[2], pixel[3]])
+        .collect()
+}
+
+pub fn is_background(pixel: [u8; 4]) -> bool {
+    pixel == [255, 255, 255, 255]
+}
+
+pub fn red() -> FormaBrush {
+    FormaBrush {
+        fill: Fill::Solid(Color {
+            r: 1.,
+            g: 0.,
+            b: 0.,
+            a: 1.,
+        }),
+        ..Default::default()
+    }
+}
+
+pub fn is_red(pixel: [u8; 4]) -> bool {
+    pixel[0] > 200 && pixel[1] < 60 && pixel[2] < 60
+}
+
+pub fn is_black(pixel: [u8; 4]) -> bool {
+    pixel[..3].iter().all(|channel| *channel < 60)
+}
+
+/// The smallest `(min_x, min_y, max_x, max_y)` box around all drawn pixels
+pub fn ink_bounds(pixels: &[[u8; 4]], width: usize) -> Option<(usize, usize, usize, usize)> {
+    pixels
+        .iter()
+        .enumerate()
+        .filter(|(_, pixel)| !is_background(**pixel))
+        .map(|(index, _)| (index % width, index / width))
+        .fold(None, |bounds, (x, y)| match bounds {
+            None => Some((x, y, x, y)),
+            Some((min_x, min_y, max_x, max_y)) => {
+                Some((min_x.min(x), min_y.min(y), max_x.max(x), max_y.max(y)))
+            }
+        })
+}
+
+/// The amount of ink in the image, summed over the darkness of every pixel
+pub fn ink(pixels: &[[u8; 4]]) -> u32 {
+    pixels
+        .iter()
+        .map(|pixel| 255 - pixel[..3].iter().copied().min().unwrap_or(255) as u32)
+        .sum()
+}
+
+/// The red and the blue channel summed over the drawn pixels in `columns` of
+/// an image `width` pixels wide
+pub fn color_in(pixels: &[[u8; 4]], width: usize, columns: Range<usize>) -> (u32, u32) {
+    let mut sum = (0, 0);
+    for (index, pixel) in pixels.iter().enumerate() {
+        if columns.contains(&(index % width)) && !is_background(*pixel) {
+            sum.0 += pixel[0] as u32;
+            sum.1 += pixel[2] as u32;
+        }
+    }
+    sum
+}
@@ -0,0 +1,89 @@
+use forma::prelude::*;
+
+use tted::helpers::AffineHelpers;
+use tted::rich_text::{RichText, StyleProperty};
+use tted::types::{Rect, Size};
+
+mod common;
+use common::{is_background, Scene};
+
+const WIDTH: usize = 200;
+const HEIGHT: usize = 1000;
+
+/// The viewport covers rows 400 to 500, half of it is drawn ahead on each side
+const VIEWPORT: Rect = Rect {
+    origin: Point { x: 0., y: 400. },
+    size: Size { w: 200., h: 100. },
+};
+
+/// 40 lines of text, about 960 px high in all
+fn lines() -> Scene {
+    let mut rich_text =
+        RichText::new([StyleProperty::Font("Roboto"), StyleProperty::FontSize(20.)]);
+    for index in 0..40 {
+        if index > 0 {
+            rich_text.add_newline();
+        }
+        rich_text.add_str("HHHH");
+    }
+    Scene::new(rich_text, WIDTH, HEIGHT)
+}
+
+/// The rows with any ink in them, from the first to the last
+fn inked_rows(pixels: &[[u8; 4]]) -> Option<(usize, usize)> {
+    let inked = |y: &usize| {
+        pixels[y * WIDTH..(y + 1) * WIDTH]
+            .iter()
+            .any(|pixel| !is_background(*pixel))
+    };
+    Some(((0..HEIGHT).find(inked)?, (0..HEIGHT).rfind(inked)?))
+}
+
+/// Shows `scene` scrolled down by `scroll`
+fn show(scene: &mut Scene, scroll: f32, viewport: Option<Rect>) -> Vec<[u8; 4]> {
+    scene.show(&AffineTransform::translat(0., -scroll), viewport)
+}
+
+#[test]
+fn only_lines_around_the_viewport_are_drawn() {
+    let pixels = show(&mut lines(), 0., Some(VIEWPORT));
+
+    // Whole lines are drawn, so the ink reaches up to a line beyond the
+    // overscan. It may stop short of it by the space above the capitals.
+    let (first, last) = inked_rows(&pixels).expect("nothing was drawn");
+    assert!((320..=360).contains(&first), "first inked row {first}");
+    assert!((540..=580).contains(&last), "last inked row {last}");
+    // without a viewport, the text covers the whole canvas
+    let (first, last) = inked_rows(&show(&mut lines(), 0., None)).unwrap();
+    assert!(first < 20 && last > 900, "{first}..{last}");
+}
+
+#[test]
+fn short_scrolls_keep_the_lines_drawn_ahead() {
+    let mut scene = lines();
+    let (first, last) = inked_rows(&show(&mut scene, 0., Some(VIEWPORT))).unwrap();
+
+    // the viewport stays within the lines drawn ahead, so they move along
+    let (moved_first, moved_last) = inked_rows(&show(&mut scene, 30., Some(VIEWPORT))).unwrap();
+    assert_eq!((moved_first + 30, moved_last + 30), (first, last));
+}
+
+#[test]
+fn scrolling_draws_the_lines_that_come_into_view() {
+    let mut scene = lines();
+    show(&mut scene, 0., Some(VIEWPORT));
+
+    // a long scroll leaves the lines drawn ahead, so they are drawn anew
+    for offset in [300., -300.] {
+        let pixels = show(&mut scene, offset, Some(VIEWPORT));
+        let (first, last) = inked_rows(&pixels).expect("nothing was drawn");
+        assert!(
+            (320..=360).contains(&first),
+            "first inked row {first} at {offset}"
+        );
+        assert!(
+            (540..=580).contains(&last),
+            "last inked row {last} at {offset}"
+        );
+    }
+}
@@ -0,0 +1,77 @@
+use forma::prelude::*;
+use forma::Order;
+
+use tted::layers::LayerAllocator;
+use tted::layout_types::Widget;
+use tted::rich_text::{RichText, StyleProperty};
+use tted::types::Size;
+
+mod common;
+use common::{compose_text, is_background, render, Scene};
+
+const WIDTH: usize = 300;
+const HEIGHT: usize = 200;
+
+fn roboto(content: &str) -> RichText {
+    let mut rich_text =
+        RichText::new([StyleProperty::Font("Roboto"), StyleProperty::FontSize(24.)]);
+    rich_text.add_str(content);
+    rich_text
+}
+
+/// Wraps into several lines, with a larger word in a layer of its own
+fn paragraph() -> RichText {
+    let mut rich_text = roboto("A paragraph long enough to wrap into a ");
+    rich_text.add_single("few", StyleProperty::FontSize(30.));
+    rich_text.add_str(" lines of text");
+    rich_text
+}
+
+/// Whether every order forma supports is free
+fn all_free(layers: &LayerAllocator) -> bool {
+    layers.clone().reserve(Order::MAX.as_u32() + 1).is_ok()
+}
+
+#[test]
+fn detach_removes_the_glyphs_and_frees_the_layers() {
+    let mut scene = Scene::new(paragraph(), WIDTH, HEIGHT);
+    let drawn = scene.show(&AffineTransform::default(), None);
+    assert!(drawn.iter().any(|pixel| !is_background(*pixel)));
+
+    scene.text.detach(&mut scene.composition, &mut scene.layers);
+    assert!(scene.text.layers().is_none());
+    assert!(all_free(&scene.layers));
+    let pixels = scene.render();
+    assert!(pixels.iter().all(|pixel| is_background(*pixel)));
+
+    // the next layout brings it back
+    assert_eq!(scene.show(&AffineTransform::default(), None), drawn);
+}
+
+#[test]
+fn shorter_update_removes_the_old_layers() {
+    let mut scene = Scene::new(paragraph(), WIDTH, HEIGHT);
+    scene.show(&AffineTransform::default(), None);
+    let old = scene.text.layers().unwrap();
+
+    scene.text.update(roboto("Hi"));
+    let pixels = scene.show(&AffineTransform::default(), None);
+    let new = scene.text.layers().unwrap();
+    assert!(new.len() < old.len(), "{new:?} vs {old:?}");
+
+    // nothing of the paragraph is left over
+    let (_, mut alone) = compose_text(
+        roboto("Hi"),
+        Size::new(WIDTH as f32, HEIGHT as f32),
+        &AffineTransform::default(),
+    );
+    assert!(pixels == render(&mut alone, WIDTH, HEIGHT));
+
+    // the old range is freed with the next layout, once it is gone from the
+    // composition
+    scene.show(&AffineTransform::default(), None);
+    let mut layers = scene.layers.clone();
+    assert_eq!(layers.reserve(old.len()).unwrap().start(), old.start());
+    scene.text.detach(&mut scene.composition, &mut scene.layers);
+    assert!(all_free(&scene.layers));
+}
@@ -0,0 +1,214 @@
+use std::time::Duration;
+
+use forma::prelude::*;
+use parley::FontContext;
+
+use tted::cache::BitmapCache;
+use tted::document::Document;
+use tted::helpers::AffineHelpers;
+use tted::layers::{LayerAllocator, LayerError};
+use tted::layout_types::{Widget, WidgetContext};
+use tted::rich_text::{RichText, StyleProperty};
+use tted::text::Text;
+use tted::types::{Rect, Size};
+
+mod common;
+use common::font_context;
+
+const WIDTH: f32 = 300.;
+const VIEWPORT: f32 = 100.;
+
+fn roboto() -> RichText {
+    RichText::new([StyleProperty::Font("Roboto"), StyleProperty::FontSize(16.)])
+}
+
+/// `count` paragraphs of one short line each
+fn lines(count: usize) -> RichText {
+    let mut text = roboto();
+    for index in 0..count {
+        if index > 0 {
+            text.add_newline();
+        }
+        text.add_str("A single line");
+    }
+    text
+}
+
+/// Lays out and composes `document` with the viewport scrolled down to `scroll`
+fn show(
+    document: &mut Document,
+    font_context: &mut FontContext,
+    layers: &mut LayerAllocator,
+    composition: &mut Composition,
+    scroll: f32,
+) -> Result<Size, LayerError> {
+    let transform = AffineTransform::translat(0., -scroll);
+    let mut ctx = WidgetContext {
+        font_context,
+        transform: &transform,
+        layers,
+        bitmaps: &BitmapCache::new(),
+        clip: None,
+        viewport: Some(Rect::new(Point::new(0., 0.), Size::new(WIDTH, VIEWPORT))),
+    };
+    let size = document.layout(&mut ctx, Size::new(WIDTH, f32::INFINITY))?;
+    document.compose(&ctx, composition, Duration::ZERO);
+    Ok(size)
+}
+
+/// The height of a single line of `roboto` text
+fn line_height(font_context: &mut FontContext) -> f32 {
+    let mut text = Text::new(lines(1));
+    let mut layers = LayerAllocator::new();
+    let transform = AffineTransform::default();
+    let mut ctx = WidgetContext {
+        font_context,
+        transform: &transform,
+        layers: &mut layers,
+        bitmaps: &BitmapCache::new(),
+        clip: None,
+        viewport: None,
+    };
+    text.layout(&mut ctx, Size::new(WIDTH, f32::INFINITY))
+        .unwrap()
+        .h
+}
+
+#[test]
+fn split_keeps_empty_lines_and_the_trailing_newline() {
+    let mut text = roboto();
+    text.add_str("a");
+    text.add_newline();
+    text.add_newline();
+    text.add_str("bc");
+    text.add_newline();
+
+    let lengths: Vec<_> = text
+        .split_paragraphs()
+        .iter()
+        .map(|paragraph| paragraph.len())
+        .collect();
+    assert_eq!(lengths, vec![1, 0, 2, 0]);
+}
+
+#[test]
+fn attributes_spanning_a_newline_are_split() {
+    let mut text = roboto();
+    text.add_str("ab");
+    text.add_single("c\n\nd", StyleProperty::FontSize(30.));
+    text.add_str("e");
+
+    let paragraphs = text.split_paragraphs();
+    assert_eq!(paragraphs.len(), 3);
+    assert_eq!(paragraphs[0].slice(0..3), "abc");
+    let ranges = |paragraph: &RichText| -> Vec<_> {
+        paragraph
+            .attributes()
+            .iter()
+            .map(|(range, _)| range.clone())
+            .collect()
+    };
+    assert_eq!(ranges(&paragraphs[0]), vec![2..3]);
+    // the empty line only takes the attribute's line height, as a default
+    assert_eq!(paragraphs[1].attribute_count(), 0);
+    assert_eq!(ranges(&paragraphs[2]), vec![0..1]);
+    assert_eq!(paragraphs[2].slice(0..2), "de");
+}
+
+#[test]
+fn unshaped_paragraphs_are_estimated_from_measured_ones() {
+    let mut font_context = font_context();
+    let line = line_height(&mut font_context);
+    let mut document = Document::new(lines(200));
+    let mut layers = LayerAllocator::new();
+    let mut composition = Composition::new();
+
+    let size = show(
+        &mut document,
+        &mut font_context,
+        &mut layers,
+        &mut composition,
+        0.,
+    )
+    .unwrap();
+
+    assert!(document.shaped_count() < 50, "{}", document.shaped_count());
+    // every paragraph is as high as the ones that were measured
+    assert!(
+        (size.h - 200. * line).abs() < 1.,
+        "{} for lines of {line}",
+        size.h
+    );
+}
+
+#[test]
+fn paragraphs_are_shaped_as_the_viewport_approaches() {
+    let mut font_context = font_context();
+    let mut document = Document::new(lines(200));
+    let mut layers = LayerAllocator::new();
+    let mut composition = Composition::new();
+
+    let size = show(
+        &mut document,
+        &mut font_context,
+        &mut layers,
+        &mut composition,
+        0.,
+    )
+    .unwrap();
+    let at_top = document.shaped_count();
+    assert!(at_top > 0);
+
+    // scrolling a little shapes the next paragraphs and keeps the ones above
+    show(
+        &mut document,
+        &mut font_context,
+        &mut layers,
+        &mut composition,
+        VIEWPORT,
+    )
+    .unwrap();
+    assert!(document.shaped_count() > at_top);
+
+    // at the end, the paragraphs at the top are far away and are dropped
+    show(
+        &mut document,
+        &mut font_context,
+        &mut layers,
+        &mut composition,
+        size.h - VIEWPORT,
+    )
+    .unwrap();
+    assert!(
+        document.shaped_count() <= at_top + 1,
+        "{} paragraphs shaped",
+        document.shaped_count()
+    );
+}
+
+#[test]
+fn distant_paragraphs_give_their_layers_back() {
+    let mut font_context = font_context();
+    let line = line_height(&mut font_context);
+    let mut document = Document::new(lines(200));
+    // Every paragraph takes a clip layer and one for its outlines. The budget
+    // covers the paragraphs around a viewport at the top and at the bottom,
+    // but not every paragraph that was ever shaped.
+    let around_viewport = (3. * VIEWPORT / line).ceil() as u32 + 2;
+    document.set_layer_budget(2 * around_viewport * 2);
+    let mut layers = LayerAllocator::new();
+    let mut composition = Composition::new();
+
+    for _ in 0..4 {
+        for scroll in [0., 200. * line - VIEWPORT] {
+            show(
+                &mut document,
+                &mut font_context,
+                &mut layers,
+                &mut composition,
+                scroll,
+            )
+            .expect("retired paragraphs should free their layers");
+        }
+    }
+}
@@ -0,0 +1,178 @@
+use std::time::Duration;
+
+use forma::prelude::*;
+use forma::Order;
+use parley::swash::zeno::Placement;
+
+use tted::cache::BitmapCache;
+use tted::conversion::convert_placement;
+use tted::helpers::AffineHelpers;
+use tted::layers::LayerAllocator;
+use tted::layout_types::{Widget, WidgetContext};
+use tted::rich_text::{RichText, StyleProperty};
+use tted::text::Text;
+use tted::types::Size;
+
+mod common;
+use common::{compose_text, font_context, ink_bounds, is_background, render};
+
+const WIDTH: usize = 300;
+const HEIGHT: usize = 100;
+
+fn solid(composition: &mut Composition, path: &Path) {
+    composition
+        .get_mut_or_insert_default(Order::new(0).unwrap())
+        .insert(path)
+        .set_props(Props {
+            fill_rule: FillRule::NonZero,
+            func: Func::Draw(Style {
+                fill: Fill::Solid(Color {
+                    r: 0.,
+                    g: 0.,
+                    b: 0.,
+                    a: 1.,
+                }),
+                ..Default::default()
+            }),
+        });
+}
+
+#[test]
+fn placement_quad_hangs_from_the_top_offset() {
+    let placement = Placement {
+        left: 2,
+        top: 30,
+        width: 32,
+        height: 36,
+    };
+    // a glyph origin at 50, 50 on screen
+    let transform =
+        AffineTransform::translat(50., 50.).concat(&AffineTransform::new_mirror(false, true));
+    let mut composition = Composition::new();
+    solid(&mut composition, &convert_placement(&placement, &transform));
+    let pixels = render(&mut composition, WIDTH, HEIGHT);
+
+    assert_eq!(ink_bounds(&pixels, WIDTH), Some((52, 20, 83, 55)));
+}
+
+#[test]
+fn placement_quad_follows_the_bitmap_scale() {
+    let placement = Placement {
+        left: -4,
+        top: 60,
+        width: 64,
+        height: 72,
+    };
+    // a bitmap rasterized at twice the pixel size of the text
+    let transform = AffineTransform::translat(50., 50.)
+        .concat(&AffineTransform::new_mirror(false, true))
+        .scaled(0.5);
+    let mut composition = Composition::new();
+    solid(&mut composition, &convert_placement(&placement, &transform));
+    let pixels = render(&mut composition, WIDTH, HEIGHT);
+
+    assert_eq!(ink_bounds(&pixels, WIDTH), Some((48, 20, 79, 55)));
+}
+
+/// Emoji sit on the baseline between the letters around them
+#[test]
+fn emoji_sits_inline_with_the_text() {
+    let font_size = 40.;
+    let mut rich_text = RichText::new([
+        StyleProperty::Font("Roboto"),
+        StyleProperty::FontSize(font_size),
+    ]);
+    rich_text.add_str("H");
+    rich_text.add_single("A", StyleProperty::Font("Sbix Test"));
+    rich_text.add_str("H");
+    let (_, mut composition) = compose_text(
+        rich_text,
+        Size::new(WIDTH as f32, HEIGHT as f32),
+        &AffineTransform::translat(10., 10.),
+    );
+    let pixels = render(&mut composition, WIDTH, HEIGHT);
+
+    // The letters are gray, the emoji is the only colored ink
+    let colored = |pixel: [u8; 4]| {
+        let (min, max) = (pixel[..3].iter().min(), pixel[..3].iter().max());
+        max.zip(min).is_some_and(|(max, min)| max - min > 40)
+    };
+    let emoji: Vec<_> = pixels
+        .iter()
+        .map(|pixel| if colored(*pixel) { *pixel } else { [255; 4] })
+        .collect();
+    let letters: Vec<_> = pixels
+        .iter()
+        .map(|pixel| if colored(*pixel) { [255; 4] } else { *pixel })
+        .collect();
+    let (emoji_left, emoji_top, emoji_right, emoji_bottom) =
+        ink_bounds(&emoji, WIDTH).expect("the emoji was not drawn");
+    let (_, letter_top, _, baseline) = ink_bounds(&letters, WIDTH).expect("no letters drawn");
+
+    // the bitmap stands on the baseline, five eighths of an em high
+    assert!(
+        emoji_bottom.abs_diff(baseline) <= 1,
+        "{emoji_bottom} vs {baseline}"
+    );
+    let height = (emoji_bottom - emoji_top + 1) as f32;
+    assert!((height - font_size * 0.625).abs() <= 1.5, "{height}");
+    assert!(emoji_top > letter_top, "{emoji_top} vs {letter_top}");
+
+    // and it stays between the two letters
+    let letter_columns: Vec<_> = (0..WIDTH)
+        .filter(|x| (0..HEIGHT).any(|y| !is_background(letters[y * WIDTH + x])))
+        .collect();
+    let first_h_right = letter_columns
+        .windows(2)
+        .find(|pair| pair[1] > pair[0] + 1)
+        .map(|pair| pair[0])
+        .expect("the letters are not separated");
+    let second_h_left = *letter_columns
+        .iter()
+        .find(|x| **x > first_h_right + 1)
+        .unwrap();
+    assert!(
+        emoji_left + 1 >= first_h_right,
+        "{emoji_left} vs {first_h_right}"
+    );
+    assert!(
+        emoji_right <= second_h_left + 1,
+        "{emoji_right} vs {second_h_left}"
+    );
+}
+
+#[test]
+fn widgets_share_their_bitmaps() {
+    let emoji = || {
+        let mut rich_text = RichText::new([
+            StyleProperty::Font("Sbix Test"),
+            StyleProperty::FontSize(40.),
+        ]);
+        rich_text.add_str("AA");
+        Text::new(rich_text)
+    };
+    let mut widgets = [emoji(), emoji()];
+
+    let mut font_context = font_context();
+    let mut layers = LayerAllocator::new();
+    let mut composition = Composition::new();
+    let bitmaps = BitmapCache::new();
+    let transform = AffineTransform::default();
+    let mut ctx = WidgetContext {
+        font_context: &mut font_context,
+        transform: &transform,
+        layers: &mut layers,
+        bitmaps: &bitmaps,
+        clip: None,
+        viewport: None,
+    };
+    for widget in widgets.iter_mut() {
+        widget
+            .layout(&mut ctx, Size::new(WIDTH as f32, HEIGHT as f32))
+            .unwrap();
+        widget.compose(&ctx, &mut composition, Duration::ZERO);
+    }
+
+    // four occurrences of the glyph in two widgets, at one strike
+    assert_eq!(bitmaps.len(), 1);
+}
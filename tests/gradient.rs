@@ -0,0 +1,142 @@
+use forma::prelude::*;
+use forma::styling::GradientType;
+
+use tted::helpers::AffineHelpers;
+use tted::layout_types::{FillSpace, FormaBrush, TextFill};
+use tted::rich_text::{RichText, StyleProperty};
+use tted::types::Size;
+
+mod common;
+use common::{color_in, compose_text, ink_bounds, is_background, render};
+
+const WIDTH: usize = 320;
+const HEIGHT: usize = 140;
+
+fn red_to_blue(space: FillSpace, start: Point, end: Point) -> FormaBrush {
+    FormaBrush {
+        text_fill: Some(TextFill::Gradient {
+            kind: GradientType::Linear,
+            start,
+            end,
+            stops: vec![
+                (
+                    Color {
+                        r: 1.,
+                        g: 0.,
+                        b: 0.,
+                        a: 1.,
+                    },
+                    0.,
+                ),
+                (
+                    Color {
+                        r: 0.,
+                        g: 0.,
+                        b: 1.,
+                        a: 1.,
+                    },
+                    1.,
+                ),
+            ],
+            space,
+        }),
+        ..Default::default()
+    }
+}
+
+fn compose(rich_text: RichText, transform: AffineTransform) -> Vec<[u8; 4]> {
+    let (_, mut composition) = compose_text(rich_text, Size::new(200., HEIGHT as f32), &transform);
+    render(&mut composition, WIDTH, HEIGHT)
+}
+
+fn roboto(content: &str, brush: FormaBrush) -> RichText {
+    let mut rich_text = RichText::new([
+        StyleProperty::Font("Roboto"),
+        StyleProperty::FontSize(48.),
+        StyleProperty::Brush(brush),
+    ]);
+    rich_text.add_str(content);
+    rich_text
+}
+
+#[test]
+fn gradient_sweeps_across_the_bounds() {
+    let brush = red_to_blue(FillSpace::Bounds, Point::new(0., 0.5), Point::new(1., 0.5));
+    let pixels = compose(roboto("MMMM", brush), AffineTransform::default());
+
+    let (left, _, right, _) = ink_bounds(&pixels, WIDTH).expect("nothing was drawn");
+    let (red, blue) = color_in(&pixels, WIDTH, left..left + 10);
+    assert!(red > blue * 2, "left edge is {red} red, {blue} blue");
+    let (red, blue) = color_in(&pixels, WIDTH, right - 10..right + 1);
+    assert!(blue > red * 2, "right edge is {red} red, {blue} blue");
+}
+
+#[test]
+fn gradient_continues_across_a_change_of_size() {
+    let brush = red_to_blue(FillSpace::Bounds, Point::new(0., 0.5), Point::new(1., 0.5));
+    let mut rich_text = roboto("M", brush);
+    rich_text.add_single("MM", StyleProperty::FontSize(32.));
+    rich_text.add_str("M");
+    let pixels = compose(rich_text, AffineTransform::default());
+
+    // The red share of the fully colored pixels falls evenly from left to
+    // right, the smaller word in the middle doesn't start over with red
+    let (left, _, right, _) = ink_bounds(&pixels, WIDTH).expect("nothing was drawn");
+    for x in left..=right {
+        let shares: Vec<f32> = (0..HEIGHT)
+            .map(|y| pixels[y * WIDTH + x])
+            .filter(|pixel| pixel[1] < 40)
+            .map(|pixel| pixel[0] as f32 / (pixel[0] as f32 + pixel[2] as f32).max(1.))
+            .collect();
+        if shares.len() < 3 {
+            continue;
+        }
+        let share = shares.iter().sum::<f32>() / shares.len() as f32;
+        let expected = 1. - (x - left) as f32 / (right - left) as f32;
+        assert!(
+            (share - expected).abs() < 0.2,
+            "column {x} is {share} red instead of {expected}"
+        );
+    }
+}
+
+#[test]
+fn gradient_spans_several_lines() {
+    // wraps into two lines, the gradient runs from top to bottom over both
+    let brush = red_to_blue(FillSpace::Bounds, Point::new(0.5, 0.), Point::new(0.5, 1.));
+    let pixels = compose(roboto("MMMM MMMM", brush), AffineTransform::default());
+
+    let (_, top, _, bottom) = ink_bounds(&pixels, WIDTH).expect("nothing was drawn");
+    assert!(bottom - top > 60, "the text did not wrap");
+    let row = |y: usize| -> (u32, u32) {
+        pixels[y * WIDTH..(y + 1) * WIDTH]
+            .iter()
+            .filter(|pixel| !is_background(**pixel))
+            .fold((0, 0), |sum, pixel| {
+                (sum.0 + pixel[0] as u32, sum.1 + pixel[2] as u32)
+            })
+    };
+    let first = (top..top + 10)
+        .map(row)
+        .fold((0, 0), |a, b| (a.0 + b.0, a.1 + b.1));
+    let last = (bottom - 10..bottom)
+        .map(row)
+        .fold((0, 0), |a, b| (a.0 + b.0, a.1 + b.1));
+    assert!(first.0 > first.1, "first line {first:?}");
+    assert!(last.1 > last.0, "last line {last:?}");
+}
+
+#[test]
+fn layout_space_gradient_moves_with_the_text() {
+    let brush = || red_to_blue(FillSpace::Layout, Point::new(0., 0.), Point::new(200., 0.));
+    let still = compose(roboto("MMMM", brush()), AffineTransform::default());
+    let moved = compose(roboto("MMMM", brush()), AffineTransform::translat(60., 0.));
+
+    for y in 0..HEIGHT {
+        for x in 0..WIDTH - 60 {
+            let (a, b) = (still[y * WIDTH + x], moved[y * WIDTH + x + 60]);
+            let distance = a.iter().zip(b).map(|(a, b)| a.abs_diff(b)).max().unwrap();
+            assert!(distance <= 2, "{x}, {y}: {a:?} vs {b:?}");
+        }
+    }
+}
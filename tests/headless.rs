@@ -0,0 +1,54 @@
+use forma::prelude::Color;
+use tted::headless::{render_png, render_rgba};
+use tted::rich_text::{RichText, StyleProperty};
+
+mod common;
+use common::font_context;
+
+const WHITE: Color = Color {
+    r: 1.,
+    g: 1.,
+    b: 1.,
+    a: 1.,
+};
+
+fn label() -> RichText {
+    let mut text = RichText::new([StyleProperty::Font("Roboto"), StyleProperty::FontSize(20.)]);
+    text.add_str("Thumbnail");
+    text
+}
+
+#[test]
+fn image_is_scaled_to_the_width() {
+    let mut fonts = font_context();
+    let single = render_rgba(label(), &mut fonts, 150., 1., WHITE).unwrap();
+    let double = render_rgba(label(), &mut fonts, 150., 2., WHITE).unwrap();
+
+    assert_eq!(single.width(), 150);
+    assert_eq!(double.width(), 300);
+    assert!(single.height() > 0);
+    assert!(double.height().abs_diff(single.height() * 2) <= 1);
+}
+
+#[test]
+fn text_is_drawn_on_the_background() {
+    let mut fonts = font_context();
+    let image = render_rgba(label(), &mut fonts, 150., 1., WHITE).unwrap();
+
+    let ink = image.pixels().filter(|pixel| pixel.0 != [255; 4]).count();
+    assert!(ink > 0, "no text was drawn");
+    // the corners stay free
+    assert_eq!(image.get_pixel(image.width() - 1, 0).0, [255; 4]);
+}
+
+#[test]
+fn png_matches_the_rgba_image() {
+    let mut fonts = font_context();
+    let path = std::env::temp_dir().join(format!("tted-headless-{}.png", std::process::id()));
+    render_png(label(), &mut fonts, 150., 1., WHITE, &path).unwrap();
+    let image = render_rgba(label(), &mut fonts, 150., 1., WHITE).unwrap();
+
+    let written = image::open(&path).unwrap().into_rgba8();
+    std::fs::remove_file(&path).unwrap();
+    assert_eq!(written, image);
+}
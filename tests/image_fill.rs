@@ -0,0 +1,112 @@
+use forma::prelude::*;
+use forma::styling::Image;
+
+use tted::layout_types::{FillSpace, FormaBrush, TextFill};
+use tted::rich_text::{RichText, StyleProperty};
+use tted::types::{Rect, Size};
+
+mod common;
+use common::{color_in, compose_text, ink_bounds, render};
+
+const WIDTH: usize = 320;
+const HEIGHT: usize = 140;
+
+/// Red in its left half and blue in its right half
+fn red_and_blue() -> Image {
+    Image::from_srgba(&[[255, 0, 0, 255], [0, 0, 255, 255]], 2, 1).unwrap()
+}
+
+fn image_brush(space: FillSpace, rect: Rect) -> FormaBrush {
+    FormaBrush {
+        text_fill: Some(TextFill::Image {
+            image: red_and_blue(),
+            rect,
+            space,
+        }),
+        ..Default::default()
+    }
+}
+
+fn roboto() -> RichText {
+    RichText::new([StyleProperty::Font("Roboto"), StyleProperty::FontSize(48.)])
+}
+
+fn compose(runs: &[(&str, FormaBrush)]) -> Vec<[u8; 4]> {
+    let mut rich_text = roboto();
+    for (content, brush) in runs {
+        rich_text.add_single(*content, StyleProperty::Brush(brush.clone()));
+    }
+    render_text(rich_text)
+}
+
+fn render_text(rich_text: RichText) -> Vec<[u8; 4]> {
+    let (_, mut composition) = compose_text(
+        rich_text,
+        Size::new(WIDTH as f32, HEIGHT as f32),
+        &AffineTransform::default(),
+    );
+    render(&mut composition, WIDTH, HEIGHT)
+}
+
+#[test]
+fn image_stretches_over_the_run() {
+    let unit = Rect::new(Point::new(0., 0.), Size::new(1., 1.));
+    let pixels = compose(&[("MMMM", image_brush(FillSpace::Bounds, unit))]);
+
+    let (left, _, right, _) = ink_bounds(&pixels, WIDTH).expect("nothing was drawn");
+    let middle = (left + right) / 2;
+    let (red, blue) = color_in(&pixels, WIDTH, left..middle - 4);
+    assert!(red > blue * 2, "left half is {red} red, {blue} blue");
+    let (red, blue) = color_in(&pixels, WIDTH, middle + 4..right + 1);
+    assert!(blue > red * 2, "right half is {red} red, {blue} blue");
+}
+
+#[test]
+fn image_in_text_space_spans_all_runs() {
+    // each run is placed in the whole text, so together they show the image once
+    let unit = Rect::new(Point::new(0., 0.), Size::new(1., 1.));
+    let pixels = compose(&[
+        ("MM", image_brush(FillSpace::Text, unit)),
+        ("MM", image_brush(FillSpace::Text, unit)),
+    ]);
+
+    let (left, _, right, _) = ink_bounds(&pixels, WIDTH).expect("nothing was drawn");
+    let middle = (left + right) / 2;
+    let (red, blue) = color_in(&pixels, WIDTH, left..middle - 4);
+    assert!(red > blue * 2, "first run is {red} red, {blue} blue");
+    let (red, blue) = color_in(&pixels, WIDTH, middle + 4..right + 1);
+    assert!(blue > red * 2, "second run is {red} red, {blue} blue");
+}
+
+#[test]
+fn image_in_bounds_space_starts_over_with_every_run() {
+    let unit = Rect::new(Point::new(0., 0.), Size::new(1., 1.));
+    let brush = image_brush(FillSpace::Bounds, unit);
+    let mut rich_text = roboto();
+    rich_text.add_single("MM", StyleProperty::Brush(brush.clone()));
+    rich_text.add_many(
+        "MM",
+        [StyleProperty::Brush(brush), StyleProperty::FontSize(32.)],
+    );
+    let pixels = render_text(rich_text);
+
+    // red, blue, red and blue again from left to right
+    let (left, _, right, _) = ink_bounds(&pixels, WIDTH).expect("nothing was drawn");
+    let mut colors: Vec<bool> = Vec::new();
+    for x in left..=right {
+        let (red, blue) = (0..HEIGHT)
+            .map(|y| pixels[y * WIDTH + x])
+            .filter(|pixel| pixel[1] < 40)
+            .fold((0, 0), |sum, pixel| {
+                (sum.0 + pixel[0] as u32, sum.1 + pixel[2] as u32)
+            });
+        if red + blue == 0 {
+            continue;
+        }
+        let is_red = red > blue;
+        if colors.last() != Some(&is_red) {
+            colors.push(is_red);
+        }
+    }
+    assert_eq!(colors, vec![true, false, true, false]);
+}
@@ -0,0 +1,99 @@
+use forma::prelude::*;
+use forma::PathBuilder;
+
+use tted::helpers::AffineHelpers;
+use tted::layout_types::Widget;
+use tted::rich_text::{RichText, StyleProperty};
+
+mod common;
+use common::{is_background, Scene};
+
+const WIDTH: usize = 300;
+const HEIGHT: usize = 150;
+
+/// Where a square is added to the glyph layer, in layout space
+const MARKER: Point = Point { x: 20., y: 110. };
+
+/// A short word in a layer of its own, after the clip layer
+fn pan() -> Scene {
+    let mut rich_text =
+        RichText::new([StyleProperty::Font("Roboto"), StyleProperty::FontSize(30.)]);
+    rich_text.add_str("Pan");
+    Scene::new(rich_text, WIDTH, HEIGHT)
+}
+
+/// Adds a square around `MARKER` to the paths of the glyph layer. It only
+/// stays as long as the text doesn't rebuild those paths.
+fn add_marker(scene: &mut Scene) {
+    let order = scene
+        .text
+        .layers()
+        .and_then(|layers| layers.order(1))
+        .unwrap();
+    let (x, y) = (MARKER.x - 10., MARKER.y - 10.);
+    let mut builder = PathBuilder::new();
+    builder.move_to(Point::new(x, y));
+    builder.line_to(Point::new(x + 20., y));
+    builder.line_to(Point::new(x + 20., y + 20.));
+    builder.line_to(Point::new(x, y + 20.));
+    builder.line_to(Point::new(x, y));
+    scene
+        .composition
+        .get_mut_or_insert_default(order)
+        .insert(&builder.build());
+}
+
+/// Whether the marker is drawn where `transform` places it
+fn shows_marker(scene: &mut Scene, transform: &AffineTransform) -> bool {
+    let pixels = scene.render();
+    let center = transform.transform_point(MARKER);
+    !is_background(pixels[center.y as usize * WIDTH + center.x as usize])
+}
+
+#[test]
+fn panning_keeps_the_paths() {
+    let mut scene = pan();
+    scene.compose(&AffineTransform::default(), None);
+    add_marker(&mut scene);
+
+    for (x, y) in [(40., 10.), (-5., 20.), (100., 0.)] {
+        let transform = AffineTransform::translat(x, y);
+        scene.compose(&transform, None);
+        assert!(
+            shows_marker(&mut scene, &transform),
+            "paths rebuilt at {x}, {y}"
+        );
+    }
+}
+
+#[test]
+fn zooming_out_keeps_the_paths_until_they_get_too_detailed() {
+    let mut scene = pan();
+    scene.compose(&AffineTransform::default(), None);
+    add_marker(&mut scene);
+
+    // the paths built at scale 1 serve down to a quarter of it
+    for scale in [0.8, 0.5, 0.3] {
+        let transform = AffineTransform::translat(40., 10.).scaled(scale);
+        scene.compose(&transform, None);
+        assert!(
+            shows_marker(&mut scene, &transform),
+            "paths rebuilt at {scale}"
+        );
+    }
+    let transform = AffineTransform::translat(40., 10.).scaled(0.2);
+    scene.compose(&transform, None);
+    assert!(!shows_marker(&mut scene, &transform), "paths kept at 0.2");
+}
+
+#[test]
+fn zooming_in_rebuilds_the_paths() {
+    let mut scene = pan();
+    scene.compose(&AffineTransform::default(), None);
+    add_marker(&mut scene);
+
+    // forma's layer transforms can't scale up, so the paths are rebuilt larger
+    let transform = AffineTransform::new_scale(1.2, 1.2);
+    scene.compose(&transform, None);
+    assert!(!shows_marker(&mut scene, &transform));
+}
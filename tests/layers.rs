@@ -0,0 +1,73 @@
+use forma::Order;
+
+use tted::layers::{LayerAllocator, LayerError};
+
+#[test]
+fn reservations_are_contiguous() {
+    let mut layers = LayerAllocator::with_range(10..100);
+    let first = layers.reserve(3).unwrap();
+    let second = layers.reserve(2).unwrap();
+
+    assert_eq!((first.start(), first.end()), (10, 13));
+    assert_eq!((second.start(), second.end()), (13, 15));
+    let orders: Vec<_> = first.orders().map(|order| order.as_u32()).collect();
+    assert_eq!(orders, vec![10, 11, 12]);
+    assert_eq!(first.order(2).map(|order| order.as_u32()), Some(12));
+    assert_eq!(first.order(3), None);
+}
+
+#[test]
+fn freed_ranges_are_reused() {
+    let mut layers = LayerAllocator::with_range(0..10);
+    let first = layers.reserve(4).unwrap();
+    let second = layers.reserve(4).unwrap();
+    layers.free(first);
+
+    // the freed block is the first one large enough
+    assert_eq!(layers.reserve(3).unwrap().start(), 0);
+    // too large for what is left of it, so it comes from behind `second`
+    assert_eq!(layers.reserve(2).unwrap().start(), 8);
+    assert_eq!(layers.reserve(1).unwrap().start(), 3);
+    assert!(layers.reserve(1).is_err());
+
+    layers.free(second);
+    assert_eq!(layers.reserve(4).unwrap().start(), 4);
+}
+
+#[test]
+fn freed_neighbours_merge() {
+    let mut layers = LayerAllocator::with_range(0..9);
+    let first = layers.reserve(3).unwrap();
+    let second = layers.reserve(3).unwrap();
+    let third = layers.reserve(3).unwrap();
+
+    layers.free(first);
+    layers.free(third);
+    assert!(layers.reserve(4).is_err());
+
+    // freeing the middle joins all three blocks into one
+    layers.free(second);
+    let all = layers.reserve(9).unwrap();
+    assert_eq!((all.start(), all.len()), (0, 9));
+}
+
+#[test]
+fn orders_run_out_at_formas_limit() {
+    let mut layers = LayerAllocator::with_range(0..u32::MAX);
+    let limit = Order::MAX.as_u32() + 1;
+    assert_eq!(
+        layers.reserve(limit + 1),
+        Err(LayerError::OutOfLayers {
+            requested: limit + 1
+        })
+    );
+
+    let all = layers.reserve(limit).unwrap();
+    assert_eq!(all.order(limit - 1), Some(Order::MAX));
+    assert_eq!(
+        layers.reserve(1),
+        Err(LayerError::OutOfLayers { requested: 1 })
+    );
+    // empty reservations never fail
+    assert!(layers.reserve(0).unwrap().is_empty());
+}
@@ -0,0 +1,141 @@
+use tted::cache::BitmapCache;
+use tted::layers::LayerAllocator;
+use tted::layout_types::{Widget, WidgetContext};
+use tted::rich_text::{RichText, StyleProperty};
+use tted::snapshot::Snapshots;
+use tted::text::Text;
+use tted::types::Size;
+
+use forma::prelude::AffineTransform;
+use serde_json::Value;
+
+mod common;
+use common::font_context;
+
+fn layout(rich_text: RichText, width: f32) -> Text {
+    let mut text = Text::new(rich_text);
+    let mut font_context = font_context();
+    let mut layers = LayerAllocator::new();
+    let transform = AffineTransform::default();
+    let mut ctx = WidgetContext {
+        font_context: &mut font_context,
+        transform: &transform,
+        layers: &mut layers,
+        bitmaps: &BitmapCache::new(),
+        clip: None,
+        viewport: None,
+    };
+    text.layout(&mut ctx, Size::new(width, f32::INFINITY))
+        .unwrap();
+    text
+}
+
+fn snapshots() -> Snapshots {
+    Snapshots::new(concat!(env!("CARGO_MANIFEST_DIR"), "/tests/snapshots"))
+}
+
+fn paragraph() -> RichText {
+    let mut text = RichText::new([StyleProperty::Font("Roboto"), StyleProperty::FontSize(16.)]);
+    text.add_str("Two lines ");
+    text.add_single("of mixed", StyleProperty::FontSize(24.));
+    text.add_str(" sizes that wrap.");
+    text
+}
+
+#[test]
+fn layout_json_is_deterministic() {
+    let first = layout(paragraph(), 160.).layout_json();
+    let second = layout(paragraph(), 160.).layout_json();
+    assert_eq!(first, second);
+}
+
+#[test]
+fn layout_json_lists_every_glyph() {
+    let mut rich_text =
+        RichText::new([StyleProperty::Font("Roboto"), StyleProperty::FontSize(16.)]);
+    rich_text.add_str("abc");
+    let json = layout(rich_text, 300.).layout_json();
+
+    assert_eq!(json.matches("\"kind\": \"outline\"").count(), 3);
+    assert_eq!(json.matches("\"baseline\"").count(), 1);
+    assert_eq!(json.matches("\"source\"").count(), 1);
+    // the clip layer comes first, the glyphs share the layer after it
+    assert!(json.contains("\"layer\": 1"));
+    assert!(!json.contains("\"layer\": 0"));
+}
+
+#[test]
+fn wrapped_paragraph() {
+    let json = layout(paragraph(), 160.).layout_json();
+    snapshots().assert_json("wrapped_paragraph", &json);
+}
+
+#[test]
+fn runs_follow_the_styles() {
+    let json = layout(paragraph(), 1000.).layout_json();
+
+    // one line with a glyph run per style
+    assert_eq!(json.matches("\"baseline\"").count(), 1);
+    assert_eq!(json.matches("\"source\"").count(), 3);
+    // the larger run has a batch of its own
+    assert_eq!(json.matches("\"kind\": \"outlines\"").count(), 2);
+}
+
+#[test]
+fn batches_span_lines() {
+    let mut rich_text =
+        RichText::new([StyleProperty::Font("Roboto"), StyleProperty::FontSize(16.)]);
+    rich_text.add_str("abc");
+    rich_text.add_newline();
+    rich_text.add_str("abc");
+    let json = layout(rich_text, 300.).layout_json();
+
+    // both lines share a layer, culling picks the lines out of it
+    assert_eq!(json.matches("\"baseline\"").count(), 2);
+    assert_eq!(json.matches("\"kind\": \"outlines\"").count(), 1);
+}
+
+#[test]
+fn mixed_runs_split_by_glyph() {
+    // `A` is a color bitmap in the emoji font, `B` a plain outline
+    let mut rich_text = RichText::new([
+        StyleProperty::Font("Sbix Test"),
+        StyleProperty::FontSize(40.),
+    ]);
+    rich_text.add_str("BAB");
+    let json: Value = serde_json::from_str(&layout(rich_text, 300.).layout_json()).unwrap();
+
+    let layer_kind = |id: &Value| {
+        json["layers"]
+            .as_array()
+            .unwrap()
+            .iter()
+            .find(|layer| layer["layer"] == *id)
+            .and_then(|layer| layer["kind"].as_str())
+            .expect("the glyph's layer is missing")
+    };
+    let runs = json["lines"][0]["runs"].as_array().unwrap();
+    assert_eq!(runs.len(), 1);
+    let glyphs: Vec<_> = runs[0]["glyphs"]
+        .as_array()
+        .unwrap()
+        .iter()
+        .map(|glyph| {
+            let layers: Vec<_> = glyph["layers"]
+                .as_array()
+                .unwrap()
+                .iter()
+                .map(layer_kind)
+                .collect();
+            (glyph["kind"].as_str().unwrap(), layers)
+        })
+        .collect();
+    assert_eq!(
+        glyphs,
+        [
+            ("outline", vec!["outlines"]),
+            ("bitmap", vec!["bitmap"]),
+            ("outline", vec!["outlines"]),
+        ]
+    );
+}
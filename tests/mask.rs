@@ -0,0 +1,91 @@
+use forma::prelude::*;
+use forma::{Order, PathBuilder};
+
+use tted::layers::LayerAllocator;
+use tted::layout_types::Widget;
+use tted::rich_text::{RichText, StyleProperty};
+use tted::text::Text;
+use tted::types::Size;
+
+mod common;
+use common::{compose_widget, ink_bounds, is_background, render};
+
+const WIDTH: usize = 300;
+const HEIGHT: usize = 100;
+
+const RED: [u8; 4] = [255, 0, 0, 255];
+
+fn title() -> Text {
+    let mut rich_text =
+        RichText::new([StyleProperty::Font("Roboto"), StyleProperty::FontSize(60.)]);
+    rich_text.add_str("TITLE");
+    Text::new(rich_text)
+}
+
+/// Composes `text`, optionally as a mask for a red rectangle covering the canvas
+fn compose(mut text: Text, mask: bool) -> Vec<[u8; 4]> {
+    if mask {
+        text.set_clip_mask(Some(1));
+    }
+    let mut layers = LayerAllocator::new();
+    let mut composition = Composition::new();
+    compose_widget(
+        &mut text,
+        &mut composition,
+        &mut layers,
+        Size::new(WIDTH as f32, HEIGHT as f32),
+        &AffineTransform::default(),
+        None,
+        None,
+    );
+
+    // the content shown through the letters, right after the text's layers
+    let content = layers.reserve(1).unwrap();
+    assert_eq!(content.start(), text.layers().unwrap().end());
+    let mut builder = PathBuilder::new();
+    builder.move_to(Point::new(0., 0.));
+    builder.line_to(Point::new(WIDTH as f32, 0.));
+    builder.line_to(Point::new(WIDTH as f32, HEIGHT as f32));
+    builder.line_to(Point::new(0., HEIGHT as f32));
+    builder.line_to(Point::new(0., 0.));
+    composition
+        .get_mut_or_insert_default(Order::new(content.start()).unwrap())
+        .insert(&builder.build())
+        .set_props(Props {
+            fill_rule: FillRule::NonZero,
+            func: Func::Draw(Style {
+                is_clipped: true,
+                fill: Fill::Solid(Color {
+                    r: 1.,
+                    g: 0.,
+                    b: 0.,
+                    a: 1.,
+                }),
+                ..Default::default()
+            }),
+        });
+
+    render(&mut composition, WIDTH, HEIGHT)
+}
+
+#[test]
+fn content_shows_through_the_letters() {
+    let text_only = compose(title(), false);
+    let masked = compose(title(), true);
+
+    // the text itself isn't drawn, only the red content inside of it
+    assert!(masked
+        .iter()
+        .all(|pixel| is_background(*pixel) || pixel[0] == 255));
+    let red = masked.iter().filter(|pixel| **pixel == RED).count();
+    assert!(red > 0, "nothing was shown through the text");
+    assert!(red < WIDTH * HEIGHT / 2, "the content was not clipped");
+
+    // and it only shows where the glyphs are
+    assert_eq!(ink_bounds(&masked, WIDTH), ink_bounds(&text_only, WIDTH));
+    for (masked, text) in masked.iter().zip(text_only.iter()) {
+        if *masked == RED {
+            assert!(!is_background(*text));
+        }
+    }
+}
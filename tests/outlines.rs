@@ -0,0 +1,65 @@
+use forma::prelude::*;
+use forma::Order;
+
+use tted::helpers::AffineHelpers;
+use tted::rich_text::{RichText, StyleProperty};
+use tted::text::Text;
+use tted::types::Size;
+
+mod common;
+use common::{compose_text, ink_bounds, render};
+
+const WIDTH: usize = 200;
+const HEIGHT: usize = 80;
+
+/// Lays out `content` and composes it with `transform`
+fn laid_out(content: &str, transform: &AffineTransform) -> (Text, Composition) {
+    let mut rich_text =
+        RichText::new([StyleProperty::Font("Roboto"), StyleProperty::FontSize(30.)]);
+    rich_text.add_str(content);
+    compose_text(rich_text, Size::new(WIDTH as f32, HEIGHT as f32), transform)
+}
+
+#[test]
+fn outlines_follow_the_text_order() {
+    let (text, _) = laid_out("Hé!", &AffineTransform::default());
+    let outlines = text.outlines(&AffineTransform::default());
+
+    let sources: Vec<_> = outlines
+        .iter()
+        .map(|outline| outline.source.clone())
+        .collect();
+    assert_eq!(sources, vec![0..1, 1..3, 3..4]);
+    assert!(outlines
+        .windows(2)
+        .all(|pair| pair[0].position.x < pair[1].position.x));
+    assert!(outlines.iter().all(|outline| !outline.is_bitmap));
+}
+
+#[test]
+fn outlines_cover_the_rendered_glyphs() {
+    let transform = AffineTransform::translat(20., 10.);
+    let (text, mut composition) = laid_out("Shape", &transform);
+    let rendered = render(&mut composition, WIDTH, HEIGHT);
+
+    let mut outlines = Composition::new();
+    let layer = outlines.get_mut_or_insert_default(Order::new(0).unwrap());
+    for outline in text.outlines(&transform) {
+        layer.insert(&outline.path);
+    }
+    layer.set_props(Props {
+        fill_rule: FillRule::NonZero,
+        func: Func::Draw(Style {
+            fill: Fill::Solid(Color {
+                r: 0.,
+                g: 0.,
+                b: 0.,
+                a: 1.,
+            }),
+            ..Default::default()
+        }),
+    });
+    let drawn = render(&mut outlines, WIDTH, HEIGHT);
+
+    assert_eq!(ink_bounds(&drawn, WIDTH), ink_bounds(&rendered, WIDTH));
+}
@@ -0,0 +1,81 @@
+use forma::prelude::*;
+
+use tted::helpers::AffineHelpers;
+use tted::layers::LayerAllocator;
+use tted::raster::RasterMode;
+use tted::rich_text::{RichText, StyleProperty};
+use tted::text::Text;
+use tted::types::Size;
+
+mod common;
+use common::{compose_widget, ink, render};
+
+const WIDTH: usize = 300;
+const HEIGHT: usize = 100;
+
+const HYBRID: RasterMode = RasterMode::Hybrid {
+    max_pixel_size: 20.,
+};
+
+/// Renders a short word at `font_size` with `mode`, placed by `transform`
+fn render_word(mode: RasterMode, font_size: f32, transform: AffineTransform) -> Vec<[u8; 4]> {
+    let mut rich_text = RichText::new([
+        StyleProperty::Font("Roboto"),
+        StyleProperty::FontSize(font_size),
+    ]);
+    rich_text.add_str("Raster");
+    let mut text = Text::new(rich_text);
+    text.set_raster_mode(mode);
+    let mut composition = Composition::new();
+    compose_widget(
+        &mut text,
+        &mut composition,
+        &mut LayerAllocator::new(),
+        Size::new(WIDTH as f32, HEIGHT as f32),
+        &transform,
+        None,
+        None,
+    );
+    render(&mut composition, WIDTH, HEIGHT)
+}
+
+#[test]
+fn small_text_is_rasterized() {
+    let transform = AffineTransform::translat(10., 10.);
+    let outlines = render_word(RasterMode::Outlines, 16., transform);
+    let hybrid = render_word(HYBRID, 16., transform);
+
+    // the hinted masks cover about as much as the outlines, but not the same pixels
+    assert_ne!(outlines, hybrid);
+    let (outline_ink, hybrid_ink) = (ink(&outlines) as f32, ink(&hybrid) as f32);
+    assert!(
+        (outline_ink - hybrid_ink).abs() <= outline_ink * 0.15,
+        "{outline_ink} vs {hybrid_ink}"
+    );
+}
+
+#[test]
+fn text_above_the_threshold_keeps_its_outlines() {
+    let transform = AffineTransform::translat(10., 10.);
+    assert_eq!(
+        render_word(RasterMode::Outlines, 30., transform),
+        render_word(HYBRID, 30., transform)
+    );
+}
+
+#[test]
+fn threshold_applies_to_the_scaled_size() {
+    // 16 px text drawn twice as large is above the threshold
+    let zoomed = AffineTransform::translat(10., 10.).scaled(2.);
+    assert_eq!(
+        render_word(RasterMode::Outlines, 16., zoomed),
+        render_word(HYBRID, 16., zoomed)
+    );
+
+    // 30 px text drawn at half the size is below it
+    let shrunk = AffineTransform::translat(10., 10.).scaled(0.5);
+    assert_ne!(
+        render_word(RasterMode::Outlines, 30., shrunk),
+        render_word(HYBRID, 30., shrunk)
+    );
+}
@@ -0,0 +1,125 @@
+use forma::prelude::*;
+
+use tted::layout_types::StrokeJoin;
+use tted::rich_text::{RichText, StyleProperty};
+use tted::text::Text;
+use tted::types::Size;
+
+mod common;
+use common::{compose_text, ink_bounds, is_black, is_red, red, render};
+
+const WIDTH: usize = 200;
+const HEIGHT: usize = 100;
+
+fn shadow(spread: f32) -> StyleProperty {
+    StyleProperty::Shadow {
+        offset: Point::new(6., 6.),
+        brush: red(),
+        spread,
+    }
+}
+
+/// Lays out `I` at 64 pixels with `properties` and renders it
+fn laid_out<const N: usize>(properties: [StyleProperty; N]) -> (Text, Vec<[u8; 4]>) {
+    let mut rich_text =
+        RichText::new([StyleProperty::Font("Roboto"), StyleProperty::FontSize(64.)]);
+    rich_text.add_many("I", properties);
+    let (text, mut composition) = compose_text(
+        rich_text,
+        Size::new(WIDTH as f32, HEIGHT as f32),
+        &AffineTransform::default(),
+    );
+    let pixels = render(&mut composition, WIDTH, HEIGHT);
+    (text, pixels)
+}
+
+#[test]
+fn hard_shadow_is_an_offset_copy_below_the_glyphs() {
+    let (_, plain) = laid_out([]);
+    let (_, shadowed) = laid_out([shadow(0.)]);
+
+    let (left, top, right, bottom) = ink_bounds(&plain, WIDTH).expect("nothing was drawn");
+    let bounds = ink_bounds(&shadowed, WIDTH).expect("nothing was drawn");
+    assert_eq!((bounds.0, bounds.1), (left, top));
+    assert!(bounds.2 >= right + 5 && bounds.2 <= right + 7, "{bounds:?}");
+    assert!(
+        bounds.3 >= bottom + 5 && bounds.3 <= bottom + 7,
+        "{bounds:?}"
+    );
+
+    // the glyph stays on top, the shadow shows beside and below it
+    let middle = (top + bottom) / 2;
+    let center = shadowed[middle * WIDTH + (left + right) / 2];
+    assert!(is_black(center), "{center:?}");
+    let beside = shadowed[middle * WIDTH + right + 3];
+    assert!(is_red(beside), "{beside:?}");
+}
+
+#[test]
+fn spread_fades_out_the_edge() {
+    let (_, plain) = laid_out([]);
+    let (_, hard) = laid_out([shadow(0.)]);
+    let (_, soft) = laid_out([shadow(8.)]);
+
+    let hard_bounds = ink_bounds(&hard, WIDTH).expect("nothing was drawn");
+    let soft_bounds = ink_bounds(&soft, WIDTH).expect("nothing was drawn");
+    assert!(soft_bounds.2 >= hard_bounds.2 + 6, "{soft_bounds:?}");
+
+    // Right of the glyph, the middle row crosses the spread from the shadow's
+    // full color out to the background. Red on white leaves the coverage in
+    // the green channel.
+    let (_, top, right, bottom) = ink_bounds(&plain, WIDTH).expect("nothing was drawn");
+    let row = (top + bottom) / 2 * WIDTH;
+    let coverage: Vec<_> = (right + 2..right + 17)
+        .map(|x| 255 - soft[row + x][1])
+        .collect();
+    assert!(coverage[0] > 245, "{coverage:?}");
+    assert!(*coverage.last().unwrap() < 10, "{coverage:?}");
+    assert!(
+        coverage
+            .windows(2)
+            .all(|pair| pair[1] <= pair[0].saturating_add(1)),
+        "coverage grows again: {coverage:?}"
+    );
+    // the copies fade out step by step rather than with a hard edge
+    let partial = coverage
+        .iter()
+        .filter(|value| (20..=235).contains(*value))
+        .count();
+    assert!(
+        partial >= 4,
+        "only {partial} partially covered pixels: {coverage:?}"
+    );
+}
+
+#[test]
+fn shadow_layers_come_before_the_glyphs() {
+    let stroke = StyleProperty::Stroke {
+        width: 4.,
+        brush: red(),
+        join: StrokeJoin::Round,
+        fill: true,
+    };
+    let (text, _) = laid_out([shadow(4.), stroke]);
+
+    // outlines of one glyph come in drawing order
+    let kinds: Vec<_> = text
+        .outlines(&AffineTransform::default())
+        .iter()
+        .map(|outline| match (outline.is_shadow, outline.is_stroke) {
+            (true, _) => "shadow",
+            (_, true) => "stroke",
+            _ => "fill",
+        })
+        .collect();
+    assert_eq!(
+        kinds,
+        ["shadow", "shadow", "shadow", "shadow", "stroke", "fill"]
+    );
+
+    // The SVG draws the same copies. Only the innermost one of an opaque
+    // shadow is opaque, the others add up towards it.
+    let svg = text.to_svg();
+    assert_eq!(svg.matches("<path ").count(), 6);
+    assert_eq!(svg.matches("fill-opacity").count(), 3);
+}
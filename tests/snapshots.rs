@@ -0,0 +1,151 @@
+use std::time::Duration;
+
+use forma::prelude::*;
+use image::{Rgba, RgbaImage};
+use parley::style::FontWeight;
+
+use tted::cache::BitmapCache;
+use tted::layers::LayerAllocator;
+use tted::layout_types::{Widget, WidgetContext};
+use tted::rich_text::{RichText, StyleProperty};
+use tted::snapshot::{diff, SnapshotError, Snapshots};
+use tted::text::Text;
+use tted::types::{Rect, Size};
+
+mod common;
+use common::{font_context, red, render};
+
+fn snapshots() -> Snapshots {
+    Snapshots::new(concat!(env!("CARGO_MANIFEST_DIR"), "/tests/snapshots"))
+}
+
+fn roboto(size: f32) -> RichText {
+    RichText::new([StyleProperty::Font("Roboto"), StyleProperty::FontSize(size)])
+}
+
+#[test]
+fn diff_ignores_small_differences() {
+    let expected = RgbaImage::from_pixel(4, 4, Rgba([100, 100, 100, 255]));
+    let mut actual = expected.clone();
+    actual.put_pixel(1, 1, Rgba([104, 100, 100, 255]));
+
+    assert_eq!(diff(&expected, &actual, 8).differing, 0);
+    assert_eq!(diff(&expected, &actual, 2).differing, 1);
+}
+
+#[test]
+fn diff_marks_differing_pixels() {
+    let expected = RgbaImage::from_pixel(4, 4, Rgba([255, 255, 255, 255]));
+    let mut actual = expected.clone();
+    actual.put_pixel(2, 3, Rgba([0, 0, 0, 255]));
+
+    let diff = diff(&expected, &actual, 8);
+    assert_eq!(diff.differing, 1);
+    assert_eq!(diff.image.get_pixel(2, 3).0, [255, 0, 0, 255]);
+    assert_ne!(diff.image.get_pixel(0, 0).0, [255, 0, 0, 255]);
+}
+
+#[test]
+fn mismatch_writes_the_diff() {
+    let dir = std::env::temp_dir().join(format!("tted-snapshots-{}", std::process::id()));
+    let mut snapshots = Snapshots::new(&dir);
+    let white = RgbaImage::from_pixel(8, 8, Rgba([255, 255, 255, 255]));
+    let black = RgbaImage::from_pixel(8, 8, Rgba([0, 0, 0, 255]));
+
+    snapshots.set_update(true);
+    snapshots.compare_image("square", &white).unwrap();
+    snapshots.set_update(false);
+    assert!(snapshots.compare_image("square", &white).is_ok());
+    assert!(snapshots.compare_image("square", &black).is_err());
+    assert!(dir.join("square.diff.png").exists());
+    assert!(dir.join("square.new.png").exists());
+
+    std::fs::remove_dir_all(&dir).unwrap();
+}
+
+#[test]
+fn missing_reference_fails() {
+    let dir = std::env::temp_dir().join(format!("tted-missing-{}", std::process::id()));
+    let mut snapshots = Snapshots::new(&dir);
+    snapshots.set_update(false);
+    let white = RgbaImage::from_pixel(8, 8, Rgba([255, 255, 255, 255]));
+
+    assert!(matches!(
+        snapshots.compare_image("nothing", &white),
+        Err(SnapshotError::Missing { .. })
+    ));
+    assert!(!dir.join("nothing.png").exists());
+    assert!(dir.join("nothing.new.png").exists());
+    assert!(matches!(
+        snapshots.compare_json("nothing", "{}\n"),
+        Err(SnapshotError::Missing { .. })
+    ));
+    assert!(!dir.join("nothing.json").exists());
+
+    std::fs::remove_dir_all(&dir).unwrap();
+}
+
+#[test]
+fn styles() {
+    let mut text = roboto(18.);
+    text.add_str("Regular ");
+    text.add_single("bold ", StyleProperty::FontWeight(FontWeight::BOLD));
+    text.add_single("large ", StyleProperty::FontSize(32.));
+    text.add_single("red", StyleProperty::Brush(red()));
+    snapshots().assert_text("styles", text, &mut font_context(), 320., 1.);
+}
+
+#[test]
+fn underline() {
+    let mut text = roboto(24.);
+    text.add_str("Some ");
+    text.add_single("underlined", StyleProperty::Underline(true));
+    text.add_str(" text");
+    snapshots().assert_text("underline", text, &mut font_context(), 320., 1.);
+}
+
+#[test]
+fn wrapping() {
+    let mut text = roboto(16.);
+    text.add_str("A longer paragraph that has to wrap onto several lines at this width.");
+    text.add_newline();
+    text.add_str("And a second paragraph.");
+    snapshots().assert_text("wrapping", text, &mut font_context(), 200., 1.);
+}
+
+#[test]
+fn hidpi() {
+    let mut text = roboto(16.);
+    text.add_str("Scaled twice");
+    snapshots().assert_text("hidpi", text, &mut font_context(), 160., 2.);
+}
+
+#[test]
+fn clipping() {
+    let (width, height) = (240, 80);
+    let mut text = roboto(32.);
+    text.add_str("Clipped text");
+    text.add_newline();
+    text.add_str("Clipped text");
+    let mut text = Text::new(text);
+
+    let mut font_context = font_context();
+    let mut layers = LayerAllocator::new();
+    let mut composition = Composition::new();
+    let transform = AffineTransform::default();
+    let mut ctx = WidgetContext {
+        font_context: &mut font_context,
+        transform: &transform,
+        layers: &mut layers,
+        bitmaps: &BitmapCache::new(),
+        clip: Some(Rect::new(Point::new(20.5, 10.5), Size::new(150., 50.))),
+        viewport: None,
+    };
+    text.layout(&mut ctx, Size::new(width as f32, height as f32))
+        .unwrap();
+    text.compose(&ctx, &mut composition, Duration::ZERO);
+    let pixels = render(&mut composition, width, height);
+
+    let image = RgbaImage::from_raw(width as u32, height as u32, pixels.concat()).unwrap();
+    snapshots().assert_image("clipping", &image);
+}
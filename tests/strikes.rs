@@ -0,0 +1,64 @@
+use forma::prelude::*;
+
+use tted::helpers::AffineHelpers;
+use tted::rich_text::{RichText, StyleProperty};
+
+mod common;
+use common::fonts::STRIKES;
+use common::{is_background, Scene};
+
+const WIDTH: usize = 300;
+const HEIGHT: usize = 150;
+
+/// A 16 px bitmap glyph that keeps its layers across zoom levels
+fn zoom() -> Scene {
+    let mut rich_text = RichText::new([
+        StyleProperty::Font("Sbix Test"),
+        StyleProperty::FontSize(16.),
+    ]);
+    rich_text.add_str("A");
+    Scene::new(rich_text, WIDTH, HEIGHT)
+}
+
+/// The pixels per em of the strike the glyph is drawn from at `scale`
+fn strike_at(scene: &mut Scene, scale: f32) -> u16 {
+    let pixels = scene.show(&AffineTransform::translat(10., 10.).scaled(scale), None);
+    // every strike is filled with a color of its own
+    let count = |color: &[u8; 4]| {
+        pixels
+            .iter()
+            .filter(|pixel| pixel.iter().zip(color).all(|(a, b)| a.abs_diff(*b) <= 8))
+            .count()
+    };
+    STRIKES
+        .iter()
+        .max_by_key(|(_, color)| count(color))
+        .filter(|(_, color)| count(color) > 0)
+        .map(|(ppem, _)| *ppem)
+        .unwrap_or_else(|| {
+            let drawn = pixels.iter().filter(|pixel| !is_background(**pixel));
+            panic!("no strike drawn at {scale}, {} pixels", drawn.count())
+        })
+}
+
+#[test]
+fn zooming_in_fetches_a_larger_strike() {
+    let mut scene = zoom();
+    assert_eq!(strike_at(&mut scene, 1.), 16);
+    assert_eq!(strike_at(&mut scene, 2.), 32);
+    assert_eq!(strike_at(&mut scene, 4.), 64);
+    assert_eq!(strike_at(&mut scene, 7.), 128);
+}
+
+#[test]
+fn small_zoom_changes_keep_the_strike() {
+    let mut scene = zoom();
+    assert_eq!(strike_at(&mut scene, 1.), 16);
+    // up to one and a half times the strike size
+    assert_eq!(strike_at(&mut scene, 1.4), 16);
+    assert_eq!(strike_at(&mut scene, 1.6), 32);
+    // and back down to a third of it
+    assert_eq!(strike_at(&mut scene, 1.2), 32);
+    assert_eq!(strike_at(&mut scene, 0.7), 32);
+    assert_eq!(strike_at(&mut scene, 0.5), 16);
+}
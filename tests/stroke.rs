@@ -0,0 +1,103 @@
+use forma::prelude::*;
+
+use tted::layout_types::StrokeJoin;
+use tted::rich_text::{RichText, StyleProperty};
+use tted::text::Text;
+use tted::types::Size;
+
+mod common;
+use common::{compose_text, ink_bounds, is_background, is_black, is_red, red, render};
+
+const WIDTH: usize = 200;
+const HEIGHT: usize = 100;
+
+fn stroke(width: f32, fill: bool) -> StyleProperty {
+    StyleProperty::Stroke {
+        width,
+        brush: red(),
+        join: StrokeJoin::Round,
+        fill,
+    }
+}
+
+/// Lays out `rich_text` and renders it
+fn laid_out(rich_text: RichText) -> (Text, Vec<[u8; 4]>) {
+    let (text, mut composition) = compose_text(
+        rich_text,
+        Size::new(WIDTH as f32, HEIGHT as f32),
+        &AffineTransform::default(),
+    );
+    let pixels = render(&mut composition, WIDTH, HEIGHT);
+    (text, pixels)
+}
+
+fn roboto<const N: usize>(content: &str, properties: [StyleProperty; N]) -> RichText {
+    let mut rich_text =
+        RichText::new([StyleProperty::Font("Roboto"), StyleProperty::FontSize(64.)]);
+    rich_text.add_many(content, properties);
+    rich_text
+}
+
+#[test]
+fn stroke_surrounds_the_fill() {
+    let (_, plain) = laid_out(roboto("I", []));
+    let (_, stroked) = laid_out(roboto("I", [stroke(8., true)]));
+
+    let plain_bounds = ink_bounds(&plain, WIDTH).expect("nothing was drawn");
+    let stroked_bounds = ink_bounds(&stroked, WIDTH).expect("nothing was drawn");
+    // half of the stroke shows on every side
+    assert!(stroked_bounds.0 + 3 <= plain_bounds.0);
+    assert!(stroked_bounds.2 >= plain_bounds.2 + 3);
+
+    // the fill stays on top of the stroke
+    let (left, top, right, bottom) = plain_bounds;
+    let center = stroked[(top + bottom) / 2 * WIDTH + (left + right) / 2];
+    assert!(is_black(center), "{center:?}");
+    let edge = stroked[(top + bottom) / 2 * WIDTH + stroked_bounds.0 + 1];
+    assert!(is_red(edge), "{edge:?}");
+}
+
+#[test]
+fn stroke_without_fill_is_hollow() {
+    let (_, plain) = laid_out(roboto("I", []));
+    let (_, hollow) = laid_out(roboto("I", [stroke(1.5, false)]));
+
+    let (left, top, right, bottom) = ink_bounds(&plain, WIDTH).expect("nothing was drawn");
+    let center = hollow[(top + bottom) / 2 * WIDTH + (left + right) / 2];
+    assert!(is_background(center), "{center:?}");
+    assert!(hollow.iter().any(|pixel| is_red(*pixel)));
+    assert!(!hollow.iter().any(|pixel| is_black(*pixel)));
+}
+
+#[test]
+fn brushes_keep_the_stroke() {
+    // the stroke applies to the whole text, the brush to its second half only
+    let mut rich_text = RichText::new([
+        StyleProperty::Font("Roboto"),
+        StyleProperty::FontSize(64.),
+        stroke(4., true),
+    ]);
+    rich_text.add_str("ab");
+    rich_text.add_single("cd", StyleProperty::Brush(red()));
+    let (text, _) = laid_out(rich_text);
+
+    let outlines = text.outlines(&AffineTransform::default());
+    let strokes: Vec<_> = outlines
+        .iter()
+        .filter(|outline| outline.is_stroke)
+        .map(|outline| outline.source.clone())
+        .collect();
+    assert_eq!(strokes, vec![0..1, 1..2, 2..3, 3..4]);
+    assert_eq!(outlines.len(), 8);
+}
+
+#[test]
+fn svg_draws_the_stroke() {
+    let mut rich_text = roboto("ab", [stroke(4., true)]);
+    rich_text.add_str("c");
+    let (text, _) = laid_out(rich_text);
+
+    let svg = text.to_svg();
+    assert_eq!(svg.matches("<path ").count(), 5);
+    assert_eq!(svg.matches("fill=\"#ff0000\"").count(), 2);
+}
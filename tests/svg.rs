@@ -0,0 +1,152 @@
+use forma::prelude::*;
+
+use forma::styling::{GradientType, Image};
+
+use tted::layout_types::{FillSpace, FormaBrush, TextFill};
+use tted::rich_text::{RichText, StyleProperty};
+use tted::types::{Rect, Size};
+
+mod common;
+use common::compose_text;
+
+fn svg(rich_text: RichText) -> String {
+    let (text, _) = compose_text(
+        rich_text,
+        Size::new(300., f32::INFINITY),
+        &AffineTransform::default(),
+    );
+    text.to_svg()
+}
+
+fn roboto() -> RichText {
+    RichText::new([StyleProperty::Font("Roboto"), StyleProperty::FontSize(20.)])
+}
+
+#[test]
+fn every_glyph_becomes_a_path() {
+    let mut text = roboto();
+    text.add_str("Hello");
+    let svg = svg(text);
+
+    assert!(svg.starts_with("<svg xmlns=\"http://www.w3.org/2000/svg\""));
+    assert!(svg.trim_end().ends_with("</svg>"));
+    assert_eq!(svg.matches("<path ").count(), 5);
+    assert_eq!(svg.matches("fill=\"#000000\"").count(), 5);
+}
+
+#[test]
+fn whitespace_has_no_path() {
+    let mut text = roboto();
+    text.add_str("a b");
+    assert_eq!(svg(text).matches("<path ").count(), 2);
+}
+
+#[test]
+fn paths_use_the_brush_color() {
+    let mut text = roboto();
+    text.add_str("a");
+    text.add_single(
+        "b",
+        StyleProperty::Brush(FormaBrush {
+            fill: Fill::Solid(Color {
+                r: 1.,
+                g: 0.,
+                b: 0.,
+                a: 0.5,
+            }),
+            ..Default::default()
+        }),
+    );
+    let svg = svg(text);
+
+    assert!(svg.contains("fill=\"#ff0000\" fill-opacity=\"0.5\""));
+    assert!(svg.contains("fill=\"#000000\""));
+}
+
+#[test]
+fn paths_are_in_layout_space() {
+    let mut text = roboto();
+    text.add_str("l");
+    let svg = svg(text);
+
+    // all coordinates of the glyph lie inside the laid out box
+    let data = svg
+        .split("d=\"")
+        .nth(1)
+        .and_then(|rest| rest.split('"').next())
+        .unwrap();
+    let numbers: Vec<f32> = data
+        .split(|c: char| c.is_ascii_alphabetic() || c == ' ')
+        .filter_map(|value| value.parse().ok())
+        .collect();
+    assert!(!numbers.is_empty());
+    for pair in numbers.chunks(2) {
+        assert!((0. ..=300.).contains(&pair[0]), "{pair:?}");
+        assert!((0. ..=30.).contains(&pair[1]), "{pair:?}");
+    }
+}
+
+#[test]
+fn gradients_become_definitions() {
+    let mut text = roboto();
+    text.add_single(
+        "ab",
+        StyleProperty::Brush(FormaBrush {
+            text_fill: Some(TextFill::Gradient {
+                kind: GradientType::Linear,
+                start: Point::new(0., 0.),
+                end: Point::new(1., 0.),
+                stops: vec![
+                    (
+                        Color {
+                            r: 1.,
+                            g: 0.,
+                            b: 0.,
+                            a: 1.,
+                        },
+                        0.,
+                    ),
+                    (
+                        Color {
+                            r: 0.,
+                            g: 0.,
+                            b: 1.,
+                            a: 0.5,
+                        },
+                        1.,
+                    ),
+                ],
+                space: FillSpace::Bounds,
+            }),
+            ..Default::default()
+        }),
+    );
+    let svg = svg(text);
+
+    assert_eq!(svg.matches("<linearGradient ").count(), 1);
+    assert!(svg.contains(r##"stop-color="#ff0000" stop-opacity="1""##));
+    assert!(svg.contains(r##"stop-color="#0000ff" stop-opacity="0.5""##));
+    assert_eq!(svg.matches(r#"fill="url(#fill0)""#).count(), 2);
+    assert!(!svg.contains(r#"fill="black""#));
+}
+
+#[test]
+fn images_become_patterns() {
+    let mut text = roboto();
+    text.add_single(
+        "a",
+        StyleProperty::Brush(FormaBrush {
+            text_fill: Some(TextFill::Image {
+                image: Image::from_srgba(&[[255, 0, 0, 255], [0, 0, 255, 255]], 2, 1).unwrap(),
+                rect: Rect::new(Point::new(0., 0.), Size::new(1., 1.)),
+                space: FillSpace::Bounds,
+            }),
+            ..Default::default()
+        }),
+    );
+    let svg = svg(text);
+
+    assert_eq!(svg.matches("<pattern ").count(), 1);
+    assert!(svg.contains("href=\"data:image/png;base64,"));
+    assert_eq!(svg.matches(r#"fill="url(#fill0)""#).count(), 1);
+}
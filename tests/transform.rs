@@ -0,0 +1,164 @@
+use std::f32::consts::FRAC_PI_2;
+
+use forma::prelude::*;
+
+use tted::helpers::AffineHelpers;
+use tted::layers::LayerAllocator;
+use tted::rich_text::{RichText, StyleProperty};
+use tted::text::Text;
+use tted::types::{Rect, Size};
+
+mod common;
+use common::fonts::STRIKES;
+use common::{compose_widget, ink, ink_bounds, is_background, render};
+
+const SIZE: usize = 300;
+
+/// Renders a short label placed with `transform`
+fn render_label(transform: AffineTransform) -> Vec<[u8; 4]> {
+    let mut rich_text =
+        RichText::new([StyleProperty::Font("Roboto"), StyleProperty::FontSize(40.)]);
+    rich_text.add_str("Label");
+    let mut text = Text::new(rich_text);
+    let mut composition = Composition::new();
+    let size = Size::new(SIZE as f32, SIZE as f32);
+    compose_widget(
+        &mut text,
+        &mut composition,
+        &mut LayerAllocator::new(),
+        size,
+        &transform,
+        None,
+        Some(Rect::new(Point::new(0., 0.), size)),
+    );
+    render(&mut composition, SIZE, SIZE)
+}
+
+/// Renders the sbix `A` at 64 pixels placed with `transform`. Its bitmap is a
+/// 40 pixel square on the baseline, with the bottom right quarter left out.
+/// Returns the pixels and the glyph origin in layout space.
+fn render_bitmap(transform: &AffineTransform) -> (Vec<[u8; 4]>, Point) {
+    let mut rich_text = RichText::new([
+        StyleProperty::Font("Sbix Test"),
+        StyleProperty::FontSize(64.),
+    ]);
+    rich_text.add_str("A");
+    let mut text = Text::new(rich_text);
+    let mut composition = Composition::new();
+    let size = Size::new(SIZE as f32, SIZE as f32);
+    compose_widget(
+        &mut text,
+        &mut composition,
+        &mut LayerAllocator::new(),
+        size,
+        transform,
+        None,
+        Some(Rect::new(Point::new(0., 0.), size)),
+    );
+    let outlines = text.outlines(&AffineTransform::default());
+    assert!(outlines[0].is_bitmap);
+    (render(&mut composition, SIZE, SIZE), outlines[0].position)
+}
+
+/// Checks that the bitmap drawn with `transform` covers the square mapped
+/// through it, turned the same way
+fn assert_bitmap_follows(transform: AffineTransform) {
+    let (pixels, origin) = render_bitmap(&transform);
+    // a point of the bitmap, given as a fraction of its width from the top left
+    let place = |x: f32, y: f32| {
+        transform.transform_point(Point::new(origin.x + x * 40., origin.y - (1. - y) * 40.))
+    };
+
+    let corners = [place(0., 0.), place(1., 0.), place(1., 1.), place(0., 1.)];
+    let min = |coordinate: fn(&Point) -> f32| {
+        corners.iter().map(coordinate).fold(f32::MAX, f32::min) as usize
+    };
+    let max = |coordinate: fn(&Point) -> f32| {
+        corners.iter().map(coordinate).fold(f32::MIN, f32::max) as usize
+    };
+    let bounds = ink_bounds(&pixels, SIZE).expect("nothing was drawn");
+    let expected = (min(|p| p.x), min(|p| p.y), max(|p| p.x), max(|p| p.y));
+    assert!(close(bounds.0, expected.0, 2), "{bounds:?} vs {expected:?}");
+    assert!(close(bounds.1, expected.1, 2), "{bounds:?} vs {expected:?}");
+    assert!(close(bounds.2, expected.2, 2), "{bounds:?} vs {expected:?}");
+    assert!(close(bounds.3, expected.3, 2), "{bounds:?} vs {expected:?}");
+
+    // the bitmap is turned with its quad: the left out quarter stays at the
+    // quad's bottom right
+    let (_, color) = STRIKES.iter().find(|(ppem, _)| *ppem == 64).unwrap();
+    let pixel_at = |x: f32, y: f32| {
+        let point = place(x, y);
+        pixels[point.y as usize * SIZE + point.x as usize]
+    };
+    for (x, y) in [(0.25, 0.25), (0.75, 0.25), (0.25, 0.75)] {
+        let pixel = pixel_at(x, y);
+        assert!(
+            pixel.iter().zip(color).all(|(a, b)| a.abs_diff(*b) <= 8),
+            "{pixel:?} at {x}, {y}"
+        );
+    }
+    let pixel = pixel_at(0.75, 0.75);
+    assert!(is_background(pixel), "{pixel:?}");
+}
+
+fn extent(bounds: (usize, usize, usize, usize)) -> (usize, usize) {
+    (bounds.2 - bounds.0, bounds.3 - bounds.1)
+}
+
+fn close(a: usize, b: usize, tolerance: usize) -> bool {
+    a.abs_diff(b) <= tolerance
+}
+
+#[test]
+fn rotated_text_turns_its_bounds() {
+    let straight = render_label(AffineTransform::translat(50., 100.));
+    let rotated = render_label(AffineTransform::translat(200., 50.).rotated(FRAC_PI_2));
+
+    let (w, h) = extent(ink_bounds(&straight, SIZE).expect("nothing was drawn"));
+    let (rotated_w, rotated_h) = extent(ink_bounds(&rotated, SIZE).expect("nothing was drawn"));
+    assert!(close(w, rotated_h, 2), "{w} vs {rotated_h}");
+    assert!(close(h, rotated_w, 2), "{h} vs {rotated_w}");
+
+    // rotation keeps the area of the glyphs
+    let (ink, rotated_ink) = (ink(&straight) as f32, ink(&rotated) as f32);
+    assert!(
+        (ink - rotated_ink).abs() <= ink * 0.03,
+        "{ink} vs {rotated_ink}"
+    );
+}
+
+#[test]
+fn rotated_text_stays_at_its_origin() {
+    // a half turn around the origin puts the text above and left of it
+    let rotated = render_label(AffineTransform::translat(250., 150.).rotated(2. * FRAC_PI_2));
+    let (min_x, min_y, max_x, max_y) = ink_bounds(&rotated, SIZE).expect("nothing was drawn");
+    assert!(max_x <= 251 && min_x < 200, "{min_x}..{max_x}");
+    assert!(max_y <= 151 && min_y < 140, "{min_y}..{max_y}");
+}
+
+#[test]
+fn skewed_text_keeps_its_area() {
+    let straight = render_label(AffineTransform::translat(50., 100.));
+    let skewed = render_label(AffineTransform::translat(50., 100.).skewed(-0.3, 0.));
+
+    let (w, h) = extent(ink_bounds(&straight, SIZE).expect("nothing was drawn"));
+    let (skewed_w, skewed_h) = extent(ink_bounds(&skewed, SIZE).expect("nothing was drawn"));
+    assert!(close(h, skewed_h, 1), "{h} vs {skewed_h}");
+    assert!(skewed_w > w, "{skewed_w} vs {w}");
+
+    let (ink, skewed_ink) = (ink(&straight) as f32, ink(&skewed) as f32);
+    assert!(
+        (ink - skewed_ink).abs() <= ink * 0.03,
+        "{ink} vs {skewed_ink}"
+    );
+}
+
+#[test]
+fn rotated_bitmap_follows_the_transform() {
+    assert_bitmap_follows(AffineTransform::translat(150., 60.).rotated(0.5));
+}
+
+#[test]
+fn skewed_bitmap_follows_the_transform() {
+    assert_bitmap_follows(AffineTransform::translat(100., 100.).skewed(-0.4, 0.));
+}